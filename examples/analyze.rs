@@ -0,0 +1,76 @@
+use std::{fs, path::PathBuf};
+
+use anyhow::Result;
+use clap::Parser;
+use goo::GooFile;
+
+#[derive(Parser)]
+struct Args {
+    /// Path to the .goo file
+    input_file: PathBuf,
+
+    /// Path to output the estimated peel force of each layer as a CSV file
+    #[clap(short, long)]
+    peel_csv: Option<PathBuf>,
+
+    /// Relative peel force above which growing layers are reported
+    #[clap(long, default_value_t = 0.8)]
+    peel_threshold: f32,
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+
+    let raw_goo = fs::read(&args.input_file)?;
+    let goo = GooFile::deserialize(&raw_goo)?;
+
+    let islands = goo.islands();
+    println!("Found {} islands", islands.len());
+    for island in islands {
+        println!(
+            "  layer {:>5} at ({:.2}, {:.2}) mm, {:.3} mm²",
+            island.layer, island.centroid.0, island.centroid.1, island.area
+        );
+    }
+
+    let traps = goo.resin_traps();
+    println!("Found {} resin traps", traps.len());
+    for trap in traps {
+        println!(
+            "  layers {:>5}-{:<5} at ({:.2}, {:.2}, {:.2}) mm, {:.3} mm³",
+            trap.layers.0,
+            trap.layers.1,
+            trap.centroid.0,
+            trap.centroid.1,
+            trap.centroid.2,
+            trap.volume
+        );
+    }
+
+    let risks = goo.peel_risks(args.peel_threshold);
+    println!("Found {} layers with a high peel force", risks.len());
+    for risk in risks {
+        println!(
+            "  layer {:>5}, {:.1} mm² ({:+.1} mm², outline {:+.1} mm), relative force {:.2}",
+            risk.layer, risk.area, risk.area_change, risk.perimeter_change, risk.force
+        );
+    }
+
+    if let Some(path) = args.peel_csv {
+        let mut csv = String::from("layer,area,perimeter,area_change,perimeter_change,force\n");
+        for force in goo.peel_forces() {
+            csv.push_str(&format!(
+                "{},{},{},{},{},{}\n",
+                force.layer,
+                force.area,
+                force.perimeter,
+                force.area_change,
+                force.perimeter_change,
+                force.force
+            ));
+        }
+        fs::write(path, csv)?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,66 @@
+//! Finding areas of a layer that are not supported by the layer below.
+
+use crate::file::File;
+
+use super::Spans;
+
+/// An area of a layer that does not touch the layer below it.
+#[derive(Clone, Debug)]
+pub struct Island {
+    pub layer: usize,
+    /// Center of the island on the build plate in mm.
+    pub centroid: (f32, f32),
+    /// Area of the island in mm².
+    pub area: f32,
+    /// Number of pixels in the island.
+    pub pixels: u64,
+}
+
+impl File {
+    /// Finds every island in the print.
+    ///
+    /// An island is a connected area of exposed pixels that does not overlap any exposed pixel on the previous layer.
+    /// The first layer is always supported by the build plate.
+    pub fn islands(&self) -> Vec<Island> {
+        let (width, height) = self.resolution();
+        let (pixel_x, pixel_y) = self.pixel_size();
+
+        let mut out = Vec::new();
+        let mut below: Option<Spans> = None;
+
+        for (i, layer) in self.layers.iter().enumerate() {
+            let spans = Spans::new(layer, width, height);
+
+            if let Some(below) = &below {
+                for component in spans.components(true) {
+                    let component = component.iter().map(|&x| &spans.spans[x]);
+                    if component.clone().any(|x| below.overlaps(x)) {
+                        continue;
+                    }
+
+                    let (mut pixels, mut sum_x, mut sum_y) = (0_u64, 0.0, 0.0);
+                    for span in component {
+                        let length = span.len() as u64;
+                        pixels += length;
+                        sum_x += (span.start + span.end) as f64 / 2.0 * length as f64;
+                        sum_y += (span.y as f64 + 0.5) * length as f64;
+                    }
+
+                    out.push(Island {
+                        layer: i,
+                        centroid: (
+                            (sum_x / pixels as f64) as f32 * pixel_x,
+                            (sum_y / pixels as f64) as f32 * pixel_y,
+                        ),
+                        area: pixels as f32 * pixel_x * pixel_y,
+                        pixels,
+                    });
+                }
+            }
+
+            below = Some(spans);
+        }
+
+        out
+    }
+}
@@ -0,0 +1,182 @@
+//! Checks for common causes of failed prints.
+//!
+//! Layers are analyzed as horizontal spans of exposed pixels, built straight from the layer runs, so the pixels of a layer never have to be decoded.
+
+use std::ops::Range;
+
+use crate::{
+    encoded_layer::LayerDecoder,
+    layer_content::LayerContent,
+    misc::{Bounds, Run},
+};
+
+pub mod islands;
+pub mod peel;
+pub mod resin_traps;
+
+/// A horizontal line of exposed pixels.
+/// `start` is inclusive and `end` is exclusive.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct Span {
+    pub y: u32,
+    pub start: u32,
+    pub end: u32,
+}
+
+impl Span {
+    pub fn len(&self) -> u32 {
+        self.end - self.start
+    }
+
+    /// If the spans share any pixel columns.
+    pub fn overlaps(&self, other: &Span) -> bool {
+        self.start < other.end && other.start < self.end
+    }
+
+    /// If the spans share any pixel columns, or touch at a corner.
+    fn touches(&self, other: &Span) -> bool {
+        self.start <= other.end && other.start <= self.end
+    }
+}
+
+/// The exposed pixels of a layer, as spans sorted by row and then column.
+pub(crate) struct Spans {
+    pub spans: Vec<Span>,
+    /// Index of the first span in each row, with an extra entry at the end.
+    rows: Vec<usize>,
+}
+
+impl Spans {
+    pub fn new(layer: &LayerContent, width: u32, height: u32) -> Self {
+        let mut spans = Vec::<Span>::new();
+        let mut pixel = 0_u64;
+
+        for Run { length, value } in LayerDecoder::new(&layer.data) {
+            let end = pixel + length;
+            while value > 0 && pixel < end && pixel < width as u64 * height as u64 {
+                let y = (pixel / width as u64) as u32;
+                let start = (pixel % width as u64) as u32;
+                let row_end = (end - y as u64 * width as u64).min(width as u64) as u32;
+
+                match spans.last_mut() {
+                    Some(last) if last.y == y && last.end == start => last.end = row_end,
+                    _ => spans.push(Span {
+                        y,
+                        start,
+                        end: row_end,
+                    }),
+                }
+                pixel += (row_end - start) as u64;
+            }
+            pixel = end;
+        }
+
+        Self::from_spans(spans, height)
+    }
+
+    fn from_spans(spans: Vec<Span>, height: u32) -> Self {
+        let mut rows = vec![0; height as usize + 1];
+        for span in &spans {
+            rows[span.y as usize + 1] += 1;
+        }
+        for y in 0..height as usize {
+            rows[y + 1] += rows[y];
+        }
+
+        Self { spans, rows }
+    }
+
+    /// The indices of the spans in row `y`.
+    pub fn row_range(&self, y: u32) -> Range<usize> {
+        match self.rows.get(y as usize + 1) {
+            Some(&end) => self.rows[y as usize]..end,
+            None => 0..0,
+        }
+    }
+
+    /// The spans in row `y`.
+    pub fn row(&self, y: u32) -> &[Span] {
+        &self.spans[self.row_range(y)]
+    }
+
+    /// If any span in this layer overlaps `span`.
+    pub fn overlaps(&self, span: &Span) -> bool {
+        let row = self.row(span.y);
+        let i = row.partition_point(|x| x.end <= span.start);
+        row.get(i).is_some_and(|x| x.overlaps(span))
+    }
+
+    /// The unexposed pixels inside `bounds`, as spans.
+    pub fn complement(&self, bounds: &Bounds) -> Self {
+        let mut spans = Vec::new();
+        for y in bounds.min.1..bounds.max.1 {
+            let mut x = bounds.min.0;
+            for span in self.row(y) {
+                if span.start > x {
+                    spans.push(Span {
+                        y,
+                        start: x,
+                        end: span.start.min(bounds.max.0),
+                    });
+                }
+                x = x.max(span.end);
+            }
+            if x < bounds.max.0 {
+                spans.push(Span {
+                    y,
+                    start: x,
+                    end: bounds.max.0,
+                });
+            }
+        }
+
+        Self::from_spans(spans, bounds.max.1)
+    }
+
+    /// Groups the spans into connected areas, returning the indices of the spans in each area.
+    ///
+    /// Exposed pixels are 8-connected, so diagonal pixels belong to the same area.
+    /// Unexposed areas should be 4-connected instead, as resin can't flow between diagonal pixels.
+    pub fn components(&self, diagonal: bool) -> Vec<Vec<usize>> {
+        let mut parents = (0..self.spans.len()).collect::<Vec<_>>();
+
+        for (i, span) in self.spans.iter().enumerate() {
+            let Some(y) = span.y.checked_sub(1) else {
+                continue;
+            };
+
+            for j in self.row_range(y) {
+                let above = &self.spans[j];
+                let connected = match diagonal {
+                    true => above.touches(span),
+                    false => above.overlaps(span),
+                };
+                if connected {
+                    let (a, b) = (find(&mut parents, i), find(&mut parents, j));
+                    parents[a] = b;
+                }
+            }
+        }
+
+        let mut components = Vec::<Vec<usize>>::new();
+        let mut index = vec![usize::MAX; self.spans.len()];
+        for i in 0..self.spans.len() {
+            let root = find(&mut parents, i);
+            if index[root] == usize::MAX {
+                index[root] = components.len();
+                components.push(Vec::new());
+            }
+            components[index[root]].push(i);
+        }
+
+        components
+    }
+}
+
+pub(crate) fn find(parents: &mut [usize], mut i: usize) -> usize {
+    while parents[i] != i {
+        parents[i] = parents[parents[i]];
+        i = parents[i];
+    }
+    i
+}
@@ -0,0 +1,92 @@
+//! Estimating the force needed to peel each layer off the bottom of the vat.
+
+use crate::file::File;
+
+use super::Spans;
+
+/// The estimated peel force of a layer.
+#[derive(Clone, Debug)]
+pub struct PeelForce {
+    pub layer: usize,
+    /// Exposed area in mm².
+    pub area: f32,
+    /// Length of the outline of the exposed area in mm.
+    pub perimeter: f32,
+    /// Change in exposed area from the previous layer in mm².
+    pub area_change: f32,
+    /// Change in outline length from the previous layer in mm.
+    pub perimeter_change: f32,
+    /// Estimated peel force relative to the largest in the print, from zero to one.
+    pub force: f32,
+}
+
+impl File {
+    /// Estimates the relative peel force of every layer.
+    ///
+    /// Suction grows with the area of each connected region, and with how far resin has to flow in from the edge of the region to fill the gap.
+    /// So each region contributes its area multiplied by its area to perimeter ratio.
+    pub fn peel_forces(&self) -> Vec<PeelForce> {
+        let (width, height) = self.resolution();
+        let (pixel_x, pixel_y) = self.pixel_size();
+
+        let mut out = Vec::with_capacity(self.layers.len());
+        let (mut previous_area, mut previous_perimeter) = (0.0, 0.0);
+
+        for (i, layer) in self.layers.iter().enumerate() {
+            let spans = Spans::new(layer, width, height);
+            let (mut area, mut perimeter, mut force) = (0.0, 0.0, 0.0);
+
+            for component in spans.components(true) {
+                let (mut pixels, mut horizontal, mut vertical) = (0, 0, 0);
+                for span in component.iter().map(|&x| &spans.spans[x]) {
+                    pixels += span.len() as u64;
+                    vertical += 2;
+
+                    let covered = |y: Option<u32>| {
+                        y.map(|y| spans.row(y))
+                            .unwrap_or_default()
+                            .iter()
+                            .filter(|x| x.overlaps(span))
+                            .map(|x| x.end.min(span.end) - x.start.max(span.start))
+                            .sum::<u32>()
+                    };
+                    horizontal +=
+                        2 * span.len() - covered(span.y.checked_sub(1)) - covered(Some(span.y + 1));
+                }
+
+                let component_area = pixels as f32 * pixel_x * pixel_y;
+                let component_perimeter = horizontal as f32 * pixel_x + vertical as f32 * pixel_y;
+                area += component_area;
+                perimeter += component_perimeter;
+                force += component_area * component_area / component_perimeter;
+            }
+
+            out.push(PeelForce {
+                layer: i,
+                area,
+                perimeter,
+                area_change: area - previous_area,
+                perimeter_change: perimeter - previous_perimeter,
+                force,
+            });
+            (previous_area, previous_perimeter) = (area, perimeter);
+        }
+
+        let max = out.iter().map(|x| x.force).fold(0.0, f32::max);
+        if max > 0.0 {
+            out.iter_mut().for_each(|x| x.force /= max);
+        }
+
+        out
+    }
+
+    /// Finds the layers with a relative peel force of at least `threshold` whose exposed area or outline grew from the previous layer.
+    ///
+    /// A longer outline means new edges that have to separate from the vat, even if the area stayed the same.
+    pub fn peel_risks(&self, threshold: f32) -> Vec<PeelForce> {
+        self.peel_forces()
+            .into_iter()
+            .filter(|x| x.force >= threshold && (x.area_change > 0.0 || x.perimeter_change > 0.0))
+            .collect()
+    }
+}
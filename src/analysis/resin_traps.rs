@@ -0,0 +1,135 @@
+//! Finding hollow areas that trap uncured resin.
+
+use crate::file::File;
+
+use super::{find, Spans};
+
+/// A void inside the print that resin can't drain out of.
+#[derive(Clone, Debug)]
+pub struct ResinTrap {
+    /// The first and last layer containing the void.
+    pub layers: (usize, usize),
+    /// Center of the void in mm.
+    pub centroid: (f32, f32, f32),
+    /// Volume of the void in mm³.
+    pub volume: f32,
+}
+
+/// A connected void area on a single layer.
+#[derive(Clone, Copy)]
+struct Void {
+    open: bool,
+    pixels: u64,
+    sum: (f64, f64, f64),
+    layers: (usize, usize),
+}
+
+impl File {
+    /// Finds every void inside the print that is not connected to the resin around or above the print.
+    ///
+    /// Voids are followed through the whole layer stack, so a hollow that opens up on any layer is not a trap.
+    /// A hollow that is only open at the build plate is still a trap, as the plate seals it during the print.
+    pub fn resin_traps(&self) -> Vec<ResinTrap> {
+        let Some(bounds) = self.bounds() else {
+            return Vec::new();
+        };
+
+        let (width, height) = self.resolution();
+        let (pixel_x, pixel_y) = self.pixel_size();
+
+        let mut voids = Vec::<Void>::new();
+        let mut parents = Vec::<usize>::new();
+        let mut below: Option<(Spans, Vec<usize>)> = None;
+
+        for (i, layer) in self.layers.iter().enumerate() {
+            let spans = Spans::new(layer, width, height).complement(&bounds);
+            let z = layer.layer_position_z as f64 - self.header.layer_thickness as f64 / 2.0;
+
+            // Label the void areas on this layer
+            let mut ids = vec![0; spans.spans.len()];
+            for component in spans.components(false) {
+                let id = voids.len();
+                let mut void = Void {
+                    open: i + 1 == self.layers.len(),
+                    pixels: 0,
+                    sum: (0.0, 0.0, 0.0),
+                    layers: (i, i),
+                };
+
+                for &index in &component {
+                    let span = &spans.spans[index];
+                    let length = span.len() as u64;
+                    void.open |= span.start == bounds.min.0
+                        || span.end == bounds.max.0
+                        || span.y == bounds.min.1
+                        || span.y + 1 == bounds.max.1;
+                    void.pixels += length;
+                    void.sum.0 += (span.start + span.end) as f64 / 2.0 * length as f64;
+                    void.sum.1 += (span.y as f64 + 0.5) * length as f64;
+                    void.sum.2 += z * length as f64;
+                    ids[index] = id;
+                }
+
+                voids.push(void);
+                parents.push(id);
+            }
+
+            // Connect them to the void areas they overlap on the layer below
+            if let Some((below, below_ids)) = &below {
+                for (span, &id) in spans.spans.iter().zip(&ids) {
+                    let row = below.row_range(span.y);
+                    let start =
+                        row.start + below.row(span.y).partition_point(|x| x.end <= span.start);
+                    let overlapping = below.spans[start..row.end]
+                        .iter()
+                        .zip(&below_ids[start..row.end])
+                        .take_while(|(x, _)| x.overlaps(span));
+
+                    for (_, &other) in overlapping {
+                        let (a, b) = (find(&mut parents, id), find(&mut parents, other));
+                        parents[a] = b;
+                    }
+                }
+            }
+
+            below = Some((spans, ids));
+        }
+
+        // Combine the void areas of each connected void
+        let mut combined = vec![None::<Void>; voids.len()];
+        for (i, void) in voids.iter().enumerate() {
+            let root = find(&mut parents, i);
+            combined[root] = Some(match combined[root] {
+                None => *void,
+                Some(x) => Void {
+                    open: x.open || void.open,
+                    pixels: x.pixels + void.pixels,
+                    sum: (
+                        x.sum.0 + void.sum.0,
+                        x.sum.1 + void.sum.1,
+                        x.sum.2 + void.sum.2,
+                    ),
+                    layers: (x.layers.0.min(void.layers.0), x.layers.1.max(void.layers.1)),
+                },
+            });
+        }
+
+        combined
+            .into_iter()
+            .flatten()
+            .filter(|x| !x.open)
+            .map(|x| {
+                let pixels = x.pixels as f64;
+                ResinTrap {
+                    layers: x.layers,
+                    centroid: (
+                        (x.sum.0 / pixels) as f32 * pixel_x,
+                        (x.sum.1 / pixels) as f32 * pixel_y,
+                        (x.sum.2 / pixels) as f32,
+                    ),
+                    volume: pixels as f32 * pixel_x * pixel_y * self.header.layer_thickness,
+                }
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,527 @@
+//! Command-line tool for inspecting, checking and editing `.goo` files.
+
+use std::{
+    fs,
+    ops::Range,
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use anyhow::{bail, ensure, Context, Result};
+use clap::{Parser, Subcommand};
+use goo::{
+    diff::FieldDiff,
+    fields::Value,
+    formats::{anycubic, ctb, sl1},
+    misc::{Bounds, Run},
+    serde::DynamicSerializer,
+    GooFile, HeaderInfo, LayerDecoder,
+};
+use serde_json::json;
+
+const EXIT_CODES: &str = "\
+Exit codes:
+  0  Success
+  1  `validate` found problems, or `diff` found differences
+  2  Invalid arguments, or the command failed";
+
+#[derive(Parser)]
+#[command(name = "goo", version, about, after_help = EXIT_CODES)]
+struct Args {
+    /// Print machine readable JSON instead of text
+    #[arg(long, global = true)]
+    json: bool,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Print the header fields and a summary of a file
+    Info { file: PathBuf },
+    /// Check a file for corrupt or inconsistent data
+    Validate { file: PathBuf },
+    /// Write a file to a directory of layer PNGs and an editable manifest
+    Extract { file: PathBuf, dir: PathBuf },
+    /// Turn a directory written by `extract` back into a file
+    Pack { dir: PathBuf, output: PathBuf },
+    /// Set header fields, or layer fields with `--layers`
+    Set {
+        file: PathBuf,
+
+        /// Fields to set, as `name=value`
+        #[arg(required = true)]
+        fields: Vec<String>,
+
+        /// Layers to set the fields on: a layer (`5`), a range (`5..10` or `5..=10`) or `all`
+        #[arg(short, long)]
+        layers: Option<String>,
+
+        /// Path to write the edited file to, instead of overwriting the input
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Print statistics about each layer
+    Stats { file: PathBuf },
+    /// Compare two files
+    Diff {
+        old: PathBuf,
+        new: PathBuf,
+
+        /// Largest difference between float fields that is still considered equal
+        #[arg(short, long, default_value_t = 1e-4)]
+        tolerance: f32,
+
+        /// Directory to write diff images of the first differing layers to
+        #[arg(short, long)]
+        image_dir: Option<PathBuf>,
+
+        /// Number of differing layers to write images for
+        #[arg(long, default_value_t = 5)]
+        images: usize,
+    },
+    /// Convert between .goo, .ctb, .cbddlp, .pws, .pwmx, .sl1 and .sl1s files, picking the formats from the extensions
+    Convert { input: PathBuf, output: PathBuf },
+}
+
+/// The file formats that can be read and written, picked from the file extension.
+#[derive(Clone, Copy)]
+enum Format {
+    Goo,
+    Ctb,
+    Cbddlp,
+    Pws,
+    Pwmx,
+    Sl1,
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+    let json = args.json;
+
+    match run(args) {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::from(1),
+        Err(error) => {
+            match json {
+                true => println!("{}", json!({ "error": format!("{error:#}") })),
+                false => eprintln!("Error: {error:#}"),
+            }
+            ExitCode::from(2)
+        }
+    }
+}
+
+/// Runs the command, returning if the check it made passed.
+fn run(args: Args) -> Result<bool> {
+    let json = args.json;
+
+    match args.command {
+        Command::Info { file } => info(&load(&file)?, json)?,
+        Command::Validate { file } => return validate(&load(&file)?, json),
+        Command::Extract { file, dir } => {
+            let goo = load(&file)?;
+            goo.unpack(&dir)?;
+            report(json, goo.layers.len(), &dir)?;
+        }
+        Command::Pack { dir, output } => {
+            let goo = GooFile::pack(&dir)?;
+            save(&goo, &output)?;
+            report(json, goo.layers.len(), &output)?;
+        }
+        Command::Set {
+            file,
+            fields,
+            layers,
+            output,
+        } => {
+            let mut goo = load(&file)?;
+            set(&mut goo, &fields, layers.as_deref())?;
+            let path = output.unwrap_or(file);
+            save(&goo, &path)?;
+            report(json, goo.layers.len(), &path)?;
+        }
+        Command::Stats { file } => stats(&load(&file)?, json)?,
+        Command::Diff {
+            old,
+            new,
+            tolerance,
+            image_dir,
+            images,
+        } => {
+            return diff(
+                &load(&old)?,
+                &load(&new)?,
+                tolerance,
+                image_dir,
+                images,
+                json,
+            )
+        }
+        Command::Convert { input, output } => {
+            let goo = load(&input)?;
+            save(&goo, &output)?;
+            report(json, goo.layers.len(), &output)?;
+        }
+    }
+
+    Ok(true)
+}
+
+fn info(goo: &GooFile, json: bool) -> Result<()> {
+    let bounds = goo.bounds();
+
+    if json {
+        let header = HeaderInfo::FIELDS
+            .iter()
+            .map(|&name| {
+                (
+                    name.to_owned(),
+                    to_json(&goo.header.get_field(name).unwrap()),
+                )
+            })
+            .collect::<serde_json::Map<_, _>>();
+        return print_json(json!({
+            "header": header,
+            "layers": goo.layers.len(),
+            "print_time": goo.print_time(),
+            "bounds": bounds.map(bounds_json),
+        }));
+    }
+
+    for &name in HeaderInfo::FIELDS {
+        println!("{name}: {}", goo.header.get_field(name).unwrap());
+    }
+
+    println!();
+    println!("Layers: {}", goo.layers.len());
+    println!("Estimated print time: {}", format_time(goo.print_time()));
+    if let Some(bounds) = bounds {
+        let (pixel_x, pixel_y) = goo.pixel_size();
+        println!(
+            "Model size: {:.2} x {:.2} x {:.2} mm",
+            bounds.width() as f32 * pixel_x,
+            bounds.height() as f32 * pixel_y,
+            goo.layers.len() as f32 * goo.header.layer_thickness
+        );
+    }
+
+    Ok(())
+}
+
+fn validate(goo: &GooFile, json: bool) -> Result<bool> {
+    let (width, height) = goo.resolution();
+    let mut problems = Vec::new();
+
+    if width == 0 || height == 0 {
+        problems.push(format!("Resolution is {width}x{height}"));
+    }
+    if goo.header.layer_count as usize != goo.layers.len() {
+        problems.push(format!(
+            "Header has {} layers, but the file has {}",
+            goo.header.layer_count,
+            goo.layers.len()
+        ));
+    }
+    if goo.header.bottom_layers as usize > goo.layers.len() {
+        problems.push(format!(
+            "Header has {} bottom layers, but the file only has {} layers",
+            goo.header.bottom_layers,
+            goo.layers.len()
+        ));
+    }
+
+    let mut previous_z = 0.0;
+    for (i, layer) in goo.layers.iter().enumerate() {
+        let checksum = LayerDecoder::new(&layer.data).checksum();
+        if layer.checksum != checksum {
+            problems.push(format!(
+                "Layer {i}: checksum is {}, expected {checksum}",
+                layer.checksum
+            ));
+        }
+
+        let mut decoder = LayerDecoder::new(&layer.data);
+        let pixels = decoder
+            .by_ref()
+            .map(|Run { length, .. }| length)
+            .sum::<u64>();
+        if decoder.is_damaged() {
+            problems.push(format!("Layer {i}: image data is corrupt"));
+        } else if pixels != width as u64 * height as u64 {
+            problems.push(format!(
+                "Layer {i}: image has {pixels} pixels, expected {}",
+                width as u64 * height as u64
+            ));
+        }
+
+        if layer.layer_position_z <= previous_z {
+            problems.push(format!(
+                "Layer {i}: Z position {} mm is not above the previous layer",
+                layer.layer_position_z
+            ));
+        }
+        previous_z = layer.layer_position_z;
+
+        if layer.layer_exposure_time <= 0.0 {
+            problems.push(format!("Layer {i}: exposure time is not positive"));
+        }
+        for (distance, speed, name) in [
+            (layer.lift_distance, layer.lift_speed, "lift"),
+            (
+                layer.second_lift_distance,
+                layer.second_lift_speed,
+                "second lift",
+            ),
+            (layer.retract_distance, layer.retract_speed, "retract"),
+            (
+                layer.second_retract_distance,
+                layer.second_retract_speed,
+                "second retract",
+            ),
+        ] {
+            if distance > 0.0 && speed <= 0.0 {
+                problems.push(format!("Layer {i}: {name} speed is not positive"));
+            }
+        }
+    }
+
+    match json {
+        true => print_json(json!({ "valid": problems.is_empty(), "problems": problems }))?,
+        false if problems.is_empty() => println!("No problems found"),
+        false => problems.iter().for_each(|x| println!("{x}")),
+    }
+
+    Ok(problems.is_empty())
+}
+
+fn set(goo: &mut GooFile, fields: &[String], layers: Option<&str>) -> Result<()> {
+    let fields = fields
+        .iter()
+        .map(|x| {
+            x.split_once('=')
+                .with_context(|| format!("Expected `name=value`, got `{x}`"))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let Some(layers) = layers else {
+        for (name, value) in fields {
+            goo.header.set_field(name, value)?;
+        }
+        return Ok(());
+    };
+
+    let range = parse_layers(layers, goo.layers.len())?;
+    for layer in &mut goo.layers[range] {
+        for &(name, value) in &fields {
+            layer.set_field(name, value)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Parses a layer, a range of layers or `all`.
+fn parse_layers(layers: &str, count: usize) -> Result<Range<usize>> {
+    let parse = |x: &str| {
+        x.trim()
+            .parse::<usize>()
+            .with_context(|| format!("Invalid layer `{x}`"))
+    };
+
+    let range = if layers == "all" {
+        0..count
+    } else if let Some((start, end)) = layers.split_once("..=") {
+        parse(start)?..parse(end)? + 1
+    } else if let Some((start, end)) = layers.split_once("..") {
+        parse(start)?..parse(end)?
+    } else {
+        let layer = parse(layers)?;
+        layer..layer + 1
+    };
+
+    ensure!(
+        range.start < range.end && range.end <= count,
+        "Layers {layers} are out of bounds, the file has {count} layers"
+    );
+    Ok(range)
+}
+
+fn stats(goo: &GooFile, json: bool) -> Result<()> {
+    let stats = goo.stats();
+
+    if json {
+        let layers = stats
+            .iter()
+            .enumerate()
+            .map(|(layer, x)| {
+                json!({
+                    "layer": layer,
+                    "exposed_pixels": x.exposed_pixels,
+                    "area": x.area,
+                    "bounds": x.bounds.map(bounds_json),
+                    "compression_ratio": x.compression_ratio,
+                })
+            })
+            .collect::<Vec<_>>();
+        return print_json(json!(layers));
+    }
+
+    println!("layer  exposed pixels  area (mm²)  compression  bounds");
+    for (layer, x) in stats.iter().enumerate() {
+        let bounds = match x.bounds {
+            Some(b) => format!("({}, {})..({}, {})", b.min.0, b.min.1, b.max.0, b.max.1),
+            None => "-".to_owned(),
+        };
+        println!(
+            "{layer:>5}  {:>14}  {:>10.2}  {:>11.4}  {bounds}",
+            x.exposed_pixels, x.area, x.compression_ratio
+        );
+    }
+
+    Ok(())
+}
+
+fn diff(
+    old: &GooFile,
+    new: &GooFile,
+    tolerance: f32,
+    image_dir: Option<PathBuf>,
+    images: usize,
+    json: bool,
+) -> Result<bool> {
+    let diff = old.diff(new, tolerance);
+
+    if let Some(dir) = image_dir {
+        fs::create_dir_all(&dir)?;
+        let layers =
+            (diff.layers.iter()).filter(|x| x.pixels.as_ref().is_some_and(|x| !x.is_empty()));
+        for layer in layers.take(images) {
+            let path = dir.join(format!("layer_{:05}.png", layer.layer));
+            old.diff_image(new, layer.layer).save(path)?;
+        }
+    }
+
+    if json {
+        let fields = |fields: &[FieldDiff]| {
+            (fields.iter())
+                .map(|x| json!({ "name": x.name, "old": to_json(&x.old), "new": to_json(&x.new) }))
+                .collect::<Vec<_>>()
+        };
+        let layers = (diff.layers.iter())
+            .map(|layer| {
+                json!({
+                    "layer": layer.layer,
+                    "fields": fields(&layer.fields),
+                    "pixels": layer.pixels.as_ref().map(|x| json!({
+                        "added": x.added,
+                        "removed": x.removed,
+                        "changed": x.changed,
+                        "bounds": x.bounds.map(bounds_json),
+                    })),
+                })
+            })
+            .collect::<Vec<_>>();
+
+        print_json(json!({
+            "same": diff.is_empty(),
+            "layer_count": [diff.layer_count.0, diff.layer_count.1],
+            "header": fields(&diff.header),
+            "layers": layers,
+        }))?;
+        return Ok(diff.is_empty());
+    }
+
+    print!("{diff}");
+    Ok(diff.is_empty())
+}
+
+impl Format {
+    fn from_path(path: &Path) -> Result<Self> {
+        let extension = path
+            .extension()
+            .and_then(|x| x.to_str())
+            .unwrap_or_default();
+        Ok(match extension.to_ascii_lowercase().as_str() {
+            "goo" => Self::Goo,
+            "ctb" => Self::Ctb,
+            "cbddlp" => Self::Cbddlp,
+            "pws" => Self::Pws,
+            "pwmx" => Self::Pwmx,
+            "sl1" | "sl1s" => Self::Sl1,
+            _ => bail!("Unknown file format for `{}`", path.display()),
+        })
+    }
+}
+
+/// Reads a file in any supported format as a `GooFile`.
+fn load(path: &Path) -> Result<GooFile> {
+    let format = Format::from_path(path)?;
+    let data = fs::read(path).with_context(|| format!("Failed to read `{}`", path.display()))?;
+
+    match format {
+        Format::Goo => GooFile::deserialize(&data)
+            .with_context(|| format!("Invalid file `{}`", path.display())),
+        Format::Ctb | Format::Cbddlp => ctb::File::deserialize(&data)?.to_goo(),
+        Format::Pws | Format::Pwmx => Ok(anycubic::File::deserialize(&data)?.to_goo()),
+        Format::Sl1 => sl1::File::deserialize(&data)?.to_goo(),
+    }
+}
+
+/// Writes a `GooFile` in the format picked from the extension of `path`.
+fn save(goo: &GooFile, path: &Path) -> Result<()> {
+    let data = match Format::from_path(path)? {
+        Format::Goo => {
+            let mut serializer = DynamicSerializer::new();
+            goo.serialize(&mut serializer);
+            serializer.into_inner()
+        }
+        Format::Ctb => ctb::File::from_goo(goo, ctb::Kind::Ctb).serialize(),
+        Format::Cbddlp => ctb::File::from_goo(goo, ctb::Kind::Cbddlp).serialize(),
+        Format::Pws => anycubic::File::from_goo(goo, anycubic::VERSION_PWS).serialize(),
+        Format::Pwmx => anycubic::File::from_goo(goo, anycubic::VERSION_PWMX).serialize(),
+        Format::Sl1 => sl1::File::from_goo(goo)?.serialize()?,
+    };
+
+    fs::write(path, data).with_context(|| format!("Failed to write `{}`", path.display()))
+}
+
+fn report(json: bool, layers: usize, path: &Path) -> Result<()> {
+    match json {
+        true => print_json(json!({ "layers": layers, "path": path })),
+        false => {
+            println!("Wrote {layers} layers to `{}`", path.display());
+            Ok(())
+        }
+    }
+}
+
+fn print_json(value: serde_json::Value) -> Result<()> {
+    println!("{}", serde_json::to_string_pretty(&value)?);
+    Ok(())
+}
+
+fn to_json(value: &Value) -> serde_json::Value {
+    match value {
+        Value::Bool(x) => json!(x),
+        Value::U16(x) => json!(x),
+        Value::U32(x) => json!(x),
+        Value::F32(x) => json!(x),
+        Value::String(x) => json!(x),
+    }
+}
+
+fn bounds_json(bounds: Bounds) -> serde_json::Value {
+    json!({ "min": bounds.min, "max": bounds.max })
+}
+
+fn format_time(seconds: f32) -> String {
+    let seconds = seconds.round() as u64;
+    format!(
+        "{}h {:02}m {:02}s",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
@@ -0,0 +1,243 @@
+//! Extracting the outlines of layers as polygons, and exporting them as SVG.
+
+use std::{collections::HashMap, fmt::Write as _, fs, path::Path};
+
+use anyhow::Result;
+
+use crate::{analysis::Spans, file::File, layer_content::LayerContent};
+
+/// A closed polygon in mm.
+///
+/// Outlines wind so that their area is positive, and holes wind the other way.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Polygon {
+    pub points: Vec<(f32, f32)>,
+}
+
+impl Polygon {
+    /// The signed area of the polygon in mm², negative for holes.
+    pub fn area(&self) -> f32 {
+        let mut area = 0.0;
+        for (i, a) in self.points.iter().enumerate() {
+            let b = self.points[(i + 1) % self.points.len()];
+            area += a.0 * b.1 - b.0 * a.1;
+        }
+        area / 2.0
+    }
+
+    pub fn is_hole(&self) -> bool {
+        self.area() < 0.0
+    }
+
+    /// Removes points that move the outline by less than `tolerance` mm, using the Ramer-Douglas-Peucker algorithm.
+    pub fn simplify(&self, tolerance: f32) -> Self {
+        if self.points.len() < 4 {
+            return self.clone();
+        }
+
+        // Split the loop at the point farthest from the first, so both halves are open lines
+        let first = self.points[0];
+        let (split, _) = self
+            .points
+            .iter()
+            .enumerate()
+            .fold((0, 0.0), |best, (i, &x)| {
+                let distance = (x.0 - first.0).powi(2) + (x.1 - first.1).powi(2);
+                if distance > best.1 {
+                    (i, distance)
+                } else {
+                    best
+                }
+            });
+
+        let mut keep = vec![false; self.points.len() + 1];
+        let mut closed = self.points.clone();
+        closed.push(first);
+        keep[0] = true;
+        keep[split] = true;
+        simplify_line(&closed, 0, split, tolerance, &mut keep);
+        simplify_line(&closed, split, closed.len() - 1, tolerance, &mut keep);
+
+        Self {
+            points: (self.points.iter().zip(&keep))
+                .filter(|(_, &keep)| keep)
+                .map(|(&x, _)| x)
+                .collect(),
+        }
+    }
+}
+
+fn simplify_line(
+    points: &[(f32, f32)],
+    start: usize,
+    end: usize,
+    tolerance: f32,
+    keep: &mut [bool],
+) {
+    let (a, b) = (points[start], points[end]);
+    let length = ((b.0 - a.0).powi(2) + (b.1 - a.1).powi(2)).sqrt();
+
+    let mut farthest = (0, 0.0);
+    for (i, &p) in points.iter().enumerate().take(end).skip(start + 1) {
+        let distance = match length > 0.0 {
+            true => ((b.0 - a.0) * (a.1 - p.1) - (a.0 - p.0) * (b.1 - a.1)).abs() / length,
+            false => ((p.0 - a.0).powi(2) + (p.1 - a.1).powi(2)).sqrt(),
+        };
+        if distance > farthest.1 {
+            farthest = (i, distance);
+        }
+    }
+
+    if farthest.1 > tolerance {
+        keep[farthest.0] = true;
+        simplify_line(points, start, farthest.0, tolerance, keep);
+        simplify_line(points, farthest.0, end, tolerance, keep);
+    }
+}
+
+impl LayerContent {
+    /// Traces the outlines and holes of the exposed areas of this layer with marching squares.
+    ///
+    /// `pixel_size` is the width and height of a pixel in mm.
+    /// Points that lie on a straight line between their neighbors are left out.
+    pub fn contours(&self, width: u32, height: u32, pixel_size: (f32, f32)) -> Vec<Polygon> {
+        let spans = Spans::new(self, width, height);
+        let Some(bounds) = self.bounds(width, height) else {
+            return Vec::new();
+        };
+
+        // Grid of pixel centers, with an unexposed border
+        let (grid_width, grid_height) = (bounds.width() as usize + 2, bounds.height() as usize + 2);
+        let mut grid = vec![false; grid_width * grid_height];
+        for span in &spans.spans {
+            let row = (span.y - bounds.min.1 + 1) as usize * grid_width;
+            let start = row + (span.start - bounds.min.0 + 1) as usize;
+            grid[start..start + span.len() as usize].fill(true);
+        }
+
+        // Points are the midpoints of cell edges, stored at double scale so they are integers.
+        // Each segment keeps the exposed pixels on its left, so outlines are traced clockwise and reversed at the end.
+        let mut segments = HashMap::new();
+        let mut starts = Vec::new();
+        for y in 0..grid_height - 1 {
+            for x in 0..grid_width - 1 {
+                let corner = |dx: usize, dy: usize| grid[(y + dy) * grid_width + x + dx] as u8;
+                let case = corner(0, 0) << 3 | corner(1, 0) << 2 | corner(1, 1) << 1 | corner(0, 1);
+
+                let (x, y) = (2 * x as i32, 2 * y as i32);
+                let (top, right) = ((x + 1, y), (x + 2, y + 1));
+                let (bottom, left) = ((x + 1, y + 2), (x, y + 1));
+
+                let cell: &[_] = match case {
+                    0b0000 | 0b1111 => &[],
+                    0b1000 => &[(left, top)],
+                    0b0100 => &[(top, right)],
+                    0b0010 => &[(right, bottom)],
+                    0b0001 => &[(bottom, left)],
+                    0b0111 => &[(top, left)],
+                    0b1011 => &[(right, top)],
+                    0b1101 => &[(bottom, right)],
+                    0b1110 => &[(left, bottom)],
+                    0b1100 => &[(left, right)],
+                    0b0110 => &[(top, bottom)],
+                    0b0011 => &[(right, left)],
+                    0b1001 => &[(bottom, top)],
+                    // Diagonal pixels are connected
+                    0b1010 => &[(right, top), (left, bottom)],
+                    0b0101 => &[(top, left), (bottom, right)],
+                    _ => unreachable!(),
+                };
+                starts.extend(cell.iter().map(|x| x.0));
+                segments.extend(cell.iter().copied());
+            }
+        }
+
+        let to_mm = |(x, y): (i32, i32)| {
+            (
+                ((x as f32 / 2.0) + bounds.min.0 as f32 - 0.5) * pixel_size.0,
+                ((y as f32 / 2.0) + bounds.min.1 as f32 - 0.5) * pixel_size.1,
+            )
+        };
+
+        let mut out = Vec::new();
+        for start in starts {
+            if !segments.contains_key(&start) {
+                continue;
+            }
+
+            let mut points = vec![start];
+            let mut point = segments.remove(&start).unwrap();
+            while point != start {
+                points.push(point);
+                point = segments.remove(&point).unwrap();
+            }
+
+            let collinear = |i: usize| {
+                let a = points[(i + points.len() - 1) % points.len()];
+                let (b, c) = (points[i], points[(i + 1) % points.len()]);
+                (b.0 - a.0) * (c.1 - b.1) == (b.1 - a.1) * (c.0 - b.0)
+            };
+            let points = (0..points.len())
+                .rev()
+                .filter(|&i| !collinear(i))
+                .map(|i| to_mm(points[i]))
+                .collect();
+            out.push(Polygon { points });
+        }
+
+        out
+    }
+}
+
+impl File {
+    /// Traces the outlines and holes of a layer, see [`LayerContent::contours`].
+    pub fn contours(&self, layer: usize) -> Vec<Polygon> {
+        let (width, height) = self.resolution();
+        self.layers[layer].contours(width, height, self.pixel_size())
+    }
+
+    /// Draws the contours of a layer as an SVG document, sized to the build plate in mm.
+    ///
+    /// If `tolerance` is set, the contours are simplified first.
+    pub fn layer_svg(&self, layer: usize, tolerance: Option<f32>) -> String {
+        let (width, height) = (self.header.x_size, self.header.y_size);
+        let mut path = String::new();
+
+        for polygon in self.contours(layer) {
+            let polygon = match tolerance {
+                Some(tolerance) => polygon.simplify(tolerance),
+                None => polygon,
+            };
+
+            for (i, (x, y)) in polygon.points.iter().enumerate() {
+                let command = if i == 0 { 'M' } else { 'L' };
+                write!(path, "{command}{x} {y} ").unwrap();
+            }
+            path.push_str("Z ");
+        }
+
+        format!(
+            concat!(
+                r#"<svg xmlns="http://www.w3.org/2000/svg" width="{0}mm" height="{1}mm" viewBox="0 0 {0} {1}">"#,
+                r#"<path fill="black" fill-rule="evenodd" d="{2}"/>"#,
+                "</svg>\n"
+            ),
+            width,
+            height,
+            path.trim_end()
+        )
+    }
+
+    /// Writes the contours of every layer to `layer_00000.svg`, `layer_00001.svg`, ... in `dir`.
+    pub fn export_svgs(&self, dir: impl AsRef<Path>, tolerance: Option<f32>) -> Result<()> {
+        let dir = dir.as_ref();
+        fs::create_dir_all(dir)?;
+
+        for layer in 0..self.layers.len() {
+            let path = dir.join(format!("layer_{layer:05}.svg"));
+            fs::write(path, self.layer_svg(layer, tolerance))?;
+        }
+
+        Ok(())
+    }
+}
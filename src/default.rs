@@ -1,5 +1,8 @@
 use crate::{
-    header_info::HeaderInfo, layer_content::LayerContent, preview_image::PreviewImage,
+    header_info::HeaderInfo,
+    layer_content::LayerContent,
+    motion::{LiftSettings, MotionProfile},
+    preview_image::PreviewImage,
     serde::SizedString,
 };
 
@@ -93,3 +96,24 @@ impl Default for LayerContent {
         }
     }
 }
+
+impl Default for MotionProfile {
+    fn default() -> Self {
+        Self {
+            small_area: 100.0,
+            large_area: 2000.0,
+            small: LiftSettings {
+                lift_distance: 2.0,
+                lift_speed: 180.0,
+                second_lift_distance: 3.0,
+                second_lift_speed: 300.0,
+            },
+            large: LiftSettings {
+                lift_distance: 5.0,
+                lift_speed: 40.0,
+                second_lift_distance: 3.0,
+                second_lift_speed: 180.0,
+            },
+        }
+    }
+}
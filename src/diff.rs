@@ -0,0 +1,232 @@
+//! Comparing two files, for finding out why they print differently.
+
+use std::fmt::{self, Display};
+
+#[cfg(feature = "image")]
+use image::{Rgb, RgbImage};
+
+use crate::{
+    fields::Value, file::File, header_info::HeaderInfo, layer_content::LayerContent, misc::Bounds,
+};
+
+/// The differences between two files.
+#[derive(Clone, Debug)]
+pub struct FileDiff {
+    /// Header fields that differ, in file order.
+    pub header: Vec<FieldDiff>,
+    /// Number of layers in the old and new file.
+    pub layer_count: (usize, usize),
+    /// Layers present in both files that differ, in order.
+    pub layers: Vec<LayerDiff>,
+}
+
+/// A field with different values in the old and new file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FieldDiff {
+    pub name: &'static str,
+    pub old: Value,
+    pub new: Value,
+}
+
+/// The differences between one layer of the old and new file.
+#[derive(Clone, Debug)]
+pub struct LayerDiff {
+    pub layer: usize,
+    /// Layer parameters that differ, in file order.
+    pub fields: Vec<FieldDiff>,
+    /// Differences in the layer image, or `None` if the files have different resolutions.
+    pub pixels: Option<PixelDiff>,
+}
+
+/// Counts of the pixels that differ between two layers.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PixelDiff {
+    /// Pixels only exposed in the new file.
+    pub added: u64,
+    /// Pixels only exposed in the old file.
+    pub removed: u64,
+    /// Pixels exposed in both files, but with a different value.
+    pub changed: u64,
+    /// Smallest rectangle containing every differing pixel.
+    pub bounds: Option<Bounds>,
+}
+
+impl FileDiff {
+    /// If the files have the same header fields, layer parameters and pixels.
+    pub fn is_empty(&self) -> bool {
+        self.header.is_empty() && self.layer_count.0 == self.layer_count.1 && self.layers.is_empty()
+    }
+}
+
+impl PixelDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added == 0 && self.removed == 0 && self.changed == 0
+    }
+}
+
+/// Lists the differences one per line, or says the files are the same.
+impl Display for FileDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return writeln!(f, "Files are the same");
+        }
+
+        if self.layer_count.0 != self.layer_count.1 {
+            writeln!(
+                f,
+                "Layer count: {} -> {}",
+                self.layer_count.0, self.layer_count.1
+            )?;
+        }
+        for field in &self.header {
+            writeln!(f, "{field}")?;
+        }
+
+        writeln!(f, "{} differing layers", self.layers.len())?;
+        for layer in &self.layers {
+            writeln!(f, "  layer {}", layer.layer)?;
+            for field in &layer.fields {
+                writeln!(f, "    {field}")?;
+            }
+            if let Some(pixels) = layer.pixels.as_ref().filter(|x| !x.is_empty()) {
+                writeln!(f, "    pixels: {pixels}")?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Display for FieldDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {} -> {}", self.name, self.old, self.new)
+    }
+}
+
+impl Display for PixelDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "+{} -{} ~{}", self.added, self.removed, self.changed)?;
+        if let Some(Bounds { min, max }) = self.bounds {
+            write!(f, " in ({}, {})..({}, {})", min.0, min.1, max.0, max.1)?;
+        }
+        Ok(())
+    }
+}
+
+impl File {
+    /// Compares this file with a newer version of it.
+    ///
+    /// Float fields are considered equal if they differ by at most `tolerance`.
+    /// Pixels are only compared if both files have the same resolution.
+    /// Preview images and layer checksums are ignored.
+    pub fn diff(&self, new: &File, tolerance: f32) -> FileDiff {
+        let same_resolution = self.resolution() == new.resolution();
+        let (width, height) = self.resolution();
+
+        let layers = self
+            .layers
+            .iter()
+            .zip(&new.layers)
+            .enumerate()
+            .filter_map(|(layer, (old, new))| {
+                let fields = diff_fields(LayerContent::FIELDS, tolerance, |x| {
+                    (old.get_field(x).unwrap(), new.get_field(x).unwrap())
+                });
+                let pixels = same_resolution.then(|| match old.data == new.data {
+                    true => PixelDiff::default(),
+                    false => diff_pixels(
+                        &old.decode_pixels(width, height),
+                        &new.decode_pixels(width, height),
+                        width,
+                    ),
+                });
+
+                let changed = !fields.is_empty() || pixels.as_ref().is_some_and(|x| !x.is_empty());
+                changed.then_some(LayerDiff {
+                    layer,
+                    fields,
+                    pixels,
+                })
+            })
+            .collect();
+
+        FileDiff {
+            header: diff_fields(HeaderInfo::FIELDS, tolerance, |x| {
+                (
+                    self.header.get_field(x).unwrap(),
+                    new.header.get_field(x).unwrap(),
+                )
+            }),
+            layer_count: (self.layers.len(), new.layers.len()),
+            layers,
+        }
+    }
+
+    /// Draws the differences between a layer of this file and a newer version of it.
+    ///
+    /// Pixels only exposed in this file are red, pixels only exposed in the new file are green, and pixels exposed in both with different values are yellow.
+    /// Pixels exposed with the same value in both files are dim grey, and pixels unexposed in both are black.
+    /// Both files must have the same resolution and contain the layer.
+    #[cfg(feature = "image")]
+    pub fn diff_image(&self, new: &File, layer: usize) -> RgbImage {
+        let (width, height) = self.resolution();
+        assert_eq!(new.resolution(), (width, height));
+
+        let old = self.layers[layer].decode_pixels(width, height);
+        let new = new.layers[layer].decode_pixels(width, height);
+
+        let mut out = RgbImage::new(width, height);
+        for (pixel, (&old, &new)) in out.pixels_mut().zip(old.iter().zip(&new)) {
+            *pixel = match (old, new) {
+                (0, 0) => Rgb([0, 0, 0]),
+                (_, 0) => Rgb([255, 0, 0]),
+                (0, _) => Rgb([0, 255, 0]),
+                _ if old != new => Rgb([255, 255, 0]),
+                _ => Rgb([64, 64, 64]),
+            };
+        }
+
+        out
+    }
+}
+
+fn diff_fields(
+    names: &'static [&'static str],
+    tolerance: f32,
+    get: impl Fn(&str) -> (Value, Value),
+) -> Vec<FieldDiff> {
+    names
+        .iter()
+        .filter_map(|&name| {
+            let (old, new) = get(name);
+            let equal = match (&old, &new) {
+                (Value::F32(a), Value::F32(b)) => (a - b).abs() <= tolerance,
+                _ => old == new,
+            };
+            (!equal).then_some(FieldDiff { name, old, new })
+        })
+        .collect()
+}
+
+fn diff_pixels(old: &[u8], new: &[u8], width: u32) -> PixelDiff {
+    let mut out = PixelDiff::default();
+    for (i, (&old, &new)) in old.iter().zip(new).enumerate().filter(|(_, (a, b))| a != b) {
+        match (old, new) {
+            (0, _) => out.added += 1,
+            (_, 0) => out.removed += 1,
+            _ => out.changed += 1,
+        }
+
+        let (x, y) = (i as u32 % width, i as u32 / width);
+        let pixel = Bounds {
+            min: (x, y),
+            max: (x + 1, y + 1),
+        };
+        out.bounds = Some(match out.bounds {
+            Some(bounds) => bounds.union(&pixel),
+            None => pixel,
+        });
+    }
+
+    out
+}
@@ -19,10 +19,12 @@ pub struct LayerEncoder {
 /// Decodes a layer from the binary `.goo` layer format.
 ///
 /// This struct implements Iterator, you can just loop over it to get all the runs in the layer.
+/// Damaged data ends the iteration early instead of panicking, check `is_damaged` afterwards to tell it apart from the end of the layer.
 pub struct LayerDecoder<'a> {
     data: &'a [u8],
     color: u8,
     offset: usize,
+    damaged: bool,
 }
 
 impl LayerEncoder {
@@ -155,12 +157,18 @@ impl<'a> LayerDecoder<'a> {
             data,
             color: 0,
             offset: 0,
+            damaged: false,
         }
     }
 
     pub fn checksum(&self) -> u8 {
         calculate_checksum(self.data)
     }
+
+    /// Whether decoding stopped early because the layer data is truncated or invalid.
+    pub fn is_damaged(&self) -> bool {
+        self.damaged
+    }
 }
 
 impl Iterator for LayerDecoder<'_> {
@@ -171,6 +179,18 @@ impl Iterator for LayerDecoder<'_> {
             return None;
         }
 
+        let run = self.decode_run();
+        if run.is_none() {
+            self.damaged = true;
+            self.offset = self.data.len();
+        }
+        run
+    }
+}
+
+impl LayerDecoder<'_> {
+    /// Decodes the run at the current offset, or returns `None` if the data is truncated or invalid.
+    fn decode_run(&mut self) -> Option<Run> {
         let mut length = 0;
         let head = self.data[self.offset];
 
@@ -184,7 +204,7 @@ impl Iterator for LayerDecoder<'_> {
             0b00 => self.color = 0,
             0b01 => {
                 self.offset += 1;
-                self.color = self.data[self.offset]
+                self.color = *self.data.get(self.offset)?;
             }
             0b10 => {
                 let diff_type = head >> 4 & 0x03;
@@ -193,13 +213,13 @@ impl Iterator for LayerDecoder<'_> {
                     length = 1;
                 } else {
                     self.offset += 1;
-                    length = self.data[self.offset] as u64;
+                    length = *self.data.get(self.offset)? as u64;
                 }
-                if diff_type & 0b10 == 0 {
-                    self.color += diff_value;
+                self.color = if diff_type & 0b10 == 0 {
+                    self.color.checked_add(diff_value)?
                 } else {
-                    self.color -= diff_value;
-                }
+                    self.color.checked_sub(diff_value)?
+                };
             }
             0b11 => self.color = 0xFF,
             _ => unreachable!(),
@@ -207,27 +227,13 @@ impl Iterator for LayerDecoder<'_> {
 
         if chunk_type != 0b10 {
             let base = (head & 0x0F) as u64;
-            match chunk_length_size {
-                0b00 => length = base,
-                0b01 => {
-                    length = base + ((self.data[self.offset + 1] as u64) << 4);
-                    self.offset += 1;
-                }
-                0b10 => {
-                    length = base
-                        + ((self.data[self.offset + 1] as u64) << 12)
-                        + ((self.data[self.offset + 2] as u64) << 4);
-                    self.offset += 2;
-                }
-                0b11 => {
-                    length = base
-                        + ((self.data[self.offset + 1] as u64) << 20)
-                        + ((self.data[self.offset + 2] as u64) << 12)
-                        + ((self.data[self.offset + 3] as u64) << 4);
-                    self.offset += 3;
-                }
-                _ => unreachable!(),
-            };
+            let bytes = chunk_length_size as usize;
+            let extra = self.data.get(self.offset + 1..self.offset + 1 + bytes)?;
+            length = extra
+                .iter()
+                .fold(0, |length, &byte| (length << 8) | byte as u64);
+            length = (length << 4) + base;
+            self.offset += bytes;
         }
         self.offset += 1;
 
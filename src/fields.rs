@@ -0,0 +1,263 @@
+//! Access to header and layer fields by name.
+//!
+//! This is used for the human readable manifest written by `GooFile::unpack`, but it is also useful for tools that want to let users edit arbitrary fields.
+
+use std::fmt::{self, Display};
+
+use anyhow::{bail, Context, Result};
+
+use crate::{header_info::HeaderInfo, layer_content::LayerContent, serde::SizedString};
+
+/// The value of a single header or layer field.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Bool(bool),
+    U16(u16),
+    U32(u32),
+    F32(f32),
+    /// Text, with `\\` and `\xNN` escapes for bytes that aren't printable text.
+    String(String),
+}
+
+/// A type that can be stored in a named field.
+trait FieldType: Sized {
+    fn to_value(&self) -> Value;
+    fn parse(value: &str) -> Result<Self>;
+}
+
+macro_rules! fields {
+    ($type:ty { $($field:ident),* $(,)? }) => {
+        impl $type {
+            /// The names of all fields accessible with `get_field` and `set_field`, in file order.
+            pub const FIELDS: &'static [&'static str] = &[$(stringify!($field)),*];
+
+            /// Gets the value of the field called `name`, or `None` if there is no such field.
+            pub fn get_field(&self, name: &str) -> Option<Value> {
+                match name {
+                    $(stringify!($field) => Some(self.$field.to_value()),)*
+                    _ => None,
+                }
+            }
+
+            /// Parses `value` and stores it in the field called `name`.
+            pub fn set_field(&mut self, name: &str, value: &str) -> Result<()> {
+                match name {
+                    $(stringify!($field) => {
+                        self.$field = FieldType::parse(value)
+                            .with_context(|| format!("Invalid value for `{name}`"))?
+                    })*
+                    _ => bail!("Unknown field `{name}`"),
+                }
+                Ok(())
+            }
+        }
+    };
+}
+
+fields!(HeaderInfo {
+    version,
+    software_info,
+    software_version,
+    file_time,
+    printer_name,
+    printer_type,
+    profile_name,
+    anti_aliasing_level,
+    grey_level,
+    blur_level,
+    layer_count,
+    x_resolution,
+    y_resolution,
+    x_mirror,
+    y_mirror,
+    x_size,
+    y_size,
+    z_size,
+    layer_thickness,
+    exposure_time,
+    exposure_delay_mode,
+    turn_off_time,
+    bottom_before_lift_time,
+    bottom_after_lift_time,
+    bottom_after_retract_time,
+    before_lift_time,
+    after_lift_time,
+    after_retract_time,
+    bottom_exposure_time,
+    bottom_layers,
+    bottom_lift_distance,
+    bottom_lift_speed,
+    lift_distance,
+    lift_speed,
+    bottom_retract_distance,
+    bottom_retract_speed,
+    retract_distance,
+    retract_speed,
+    bottom_second_lift_distance,
+    bottom_second_lift_speed,
+    second_lift_distance,
+    second_lift_speed,
+    bottom_second_retract_distance,
+    bottom_second_retract_speed,
+    second_retract_distance,
+    second_retract_speed,
+    bottom_light_pwm,
+    light_pwm,
+    advance_mode,
+    printing_time,
+    total_volume,
+    total_weight,
+    total_price,
+    price_unit,
+    grey_scale_level,
+    transition_layers,
+});
+
+fields!(LayerContent {
+    pause_flag,
+    pause_position_z,
+    layer_position_z,
+    layer_exposure_time,
+    layer_off_time,
+    before_lift_time,
+    after_lift_time,
+    after_retract_time,
+    lift_distance,
+    lift_speed,
+    second_lift_distance,
+    second_lift_speed,
+    retract_distance,
+    retract_speed,
+    second_retract_distance,
+    second_retract_speed,
+    light_pwm,
+});
+
+impl FieldType for bool {
+    fn to_value(&self) -> Value {
+        Value::Bool(*self)
+    }
+
+    fn parse(value: &str) -> Result<Self> {
+        Ok(value.parse()?)
+    }
+}
+
+impl FieldType for u16 {
+    fn to_value(&self) -> Value {
+        Value::U16(*self)
+    }
+
+    fn parse(value: &str) -> Result<Self> {
+        Ok(value.parse()?)
+    }
+}
+
+impl FieldType for u32 {
+    fn to_value(&self) -> Value {
+        Value::U32(*self)
+    }
+
+    fn parse(value: &str) -> Result<Self> {
+        Ok(value.parse()?)
+    }
+}
+
+impl FieldType for f32 {
+    fn to_value(&self) -> Value {
+        Value::F32(*self)
+    }
+
+    fn parse(value: &str) -> Result<Self> {
+        Ok(value.parse()?)
+    }
+}
+
+impl<const SIZE: usize> FieldType for SizedString<SIZE> {
+    fn to_value(&self) -> Value {
+        Value::String(escape(&self.data))
+    }
+
+    fn parse(value: &str) -> Result<Self> {
+        let bytes = unescape(value)?;
+        if bytes.len() > SIZE {
+            bail!("String is longer than {SIZE} bytes");
+        }
+        Ok(SizedString::new(&bytes))
+    }
+}
+
+/// Turns the bytes of a string field into text that [`unescape`] turns back into the same bytes, ignoring trailing zeros.
+///
+/// Backslashes, control characters, invalid UTF-8 and whitespace at either end (which the manifest would trim) are written as `\\` and `\xNN` escapes.
+fn escape(data: &[u8]) -> String {
+    let end = data.iter().rposition(|&x| x != 0).map_or(0, |x| x + 1);
+    let data = &data[..end];
+
+    let mut out = String::new();
+    let mut offset = 0;
+    for chunk in data.utf8_chunks() {
+        for (i, char) in chunk.valid().char_indices() {
+            let position = offset + i;
+            let edge = position == 0 || position + char.len_utf8() == data.len();
+            if char == '\\' {
+                out.push_str("\\\\");
+            } else if char.is_control() || (edge && char.is_whitespace()) {
+                let mut buf = [0; 4];
+                for byte in char.encode_utf8(&mut buf).bytes() {
+                    out.push_str(&format!("\\x{byte:02x}"));
+                }
+            } else {
+                out.push(char);
+            }
+        }
+
+        for byte in chunk.invalid() {
+            out.push_str(&format!("\\x{byte:02x}"));
+        }
+        offset += chunk.valid().len() + chunk.invalid().len();
+    }
+
+    out
+}
+
+/// Reverses [`escape`].
+fn unescape(value: &str) -> Result<Vec<u8>> {
+    let mut out = Vec::with_capacity(value.len());
+    let mut bytes = value.bytes();
+
+    while let Some(byte) = bytes.next() {
+        if byte != b'\\' {
+            out.push(byte);
+            continue;
+        }
+
+        match bytes.next() {
+            Some(b'\\') => out.push(b'\\'),
+            Some(b'x') => {
+                let hex = [bytes.next(), bytes.next()];
+                let hex = hex
+                    .iter()
+                    .map(|x| x.map(char::from).and_then(|x| x.to_digit(16)))
+                    .collect::<Option<Vec<_>>>()
+                    .context("Expected two hex digits after `\\x`")?;
+                out.push((hex[0] * 16 + hex[1]) as u8);
+            }
+            _ => bail!("Invalid escape sequence, expected `\\\\` or `\\xNN`"),
+        }
+    }
+
+    Ok(out)
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Bool(value) => value.fmt(f),
+            Value::U16(value) => value.fmt(f),
+            Value::U32(value) => value.fmt(f),
+            Value::F32(value) => value.fmt(f),
+            Value::String(value) => f.write_str(value),
+        }
+    }
+}
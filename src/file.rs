@@ -1,4 +1,6 @@
-use anyhow::{ensure, Result};
+use std::ops::{Bound, RangeBounds};
+
+use anyhow::{bail, ensure, Result};
 
 #[cfg(feature = "chrono")]
 use chrono::Local;
@@ -6,13 +8,16 @@ use chrono::Local;
 #[allow(unused_imports)]
 use crate::{
     header_info::HeaderInfo,
-    layer_content::LayerContent,
-    misc::SliceResult,
+    layer_content::{LayerContent, Pause},
+    misc::{FileInfo, LayerInfo, SliceResult, SlicedFile},
+    plate::label_objects,
+    preview_image::PreviewImage,
     serde::{Deserializer, Serializer, SizedString},
     ENDING_STRING,
 };
 
 /// A `.goo` file.
+#[derive(Clone)]
 pub struct File {
     pub header: HeaderInfo,
     pub layers: Vec<LayerContent>,
@@ -23,6 +28,14 @@ impl File {
         Self { header, layers }
     }
 
+    /// Width and height of each layer in pixels.
+    pub fn resolution(&self) -> (u32, u32) {
+        (
+            self.header.x_resolution as u32,
+            self.header.y_resolution as u32,
+        )
+    }
+
     pub fn from_slice_result(result: SliceResult<LayerContent>) -> Self {
         let SliceResult {
             layers,
@@ -94,13 +107,395 @@ impl File {
         let mut des = Deserializer::new(buf);
 
         let header = HeaderInfo::deserialize(&mut des)?;
-        let mut layers = Vec::with_capacity(header.layer_count as usize);
+        // Don't trust the layer count of a damaged file with a huge allocation
+        let mut layers = Vec::with_capacity((header.layer_count as usize).min(buf.len()));
 
-        for _ in 0..header.layer_count {
-            layers.push(LayerContent::deserialize(&mut des)?);
+        for i in 0..header.layer_count {
+            let layer = LayerContent::deserialize(&mut des);
+            layers.push(layer.map_err(|x| x.context(format!("Layer {i}")))?);
         }
 
-        ensure!(des.read_bytes(ENDING_STRING.len()) == ENDING_STRING);
+        ensure!(des.read_bytes(ENDING_STRING.len())? == ENDING_STRING);
         Ok(Self { header, layers })
     }
 }
+
+/// Layer editing.
+///
+/// These operations keep the layer Z positions, the bottom layer count and the header layer count consistent.
+impl File {
+    /// Inserts a layer before `index`, moving all following layers up by one layer thickness.
+    ///
+    /// The Z position of the new layer is set from its neighbors, and inserting before the end of the bottom layers makes the new layer a bottom layer.
+    pub fn insert_layer(&mut self, index: usize, mut layer: LayerContent) -> Result<()> {
+        ensure!(
+            index <= self.layers.len(),
+            "Layer index {index} out of bounds"
+        );
+
+        let below = match index {
+            0 => 0.0,
+            _ => self.layers[index - 1].layer_position_z,
+        };
+        layer.layer_position_z = below + self.header.layer_thickness;
+
+        self.shift_layers(index, self.header.layer_thickness);
+        self.layers.insert(index, layer);
+
+        if (index as u32) < self.header.bottom_layers {
+            self.header.bottom_layers += 1;
+        }
+        self.update_header();
+        Ok(())
+    }
+
+    /// Removes a range of layers, moving all following layers down to close the gap.
+    pub fn remove_layers(&mut self, range: impl RangeBounds<usize>) -> Result<Vec<LayerContent>> {
+        let start = match range.start_bound() {
+            Bound::Included(&start) => start,
+            Bound::Excluded(&start) => start.saturating_add(1),
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(&end) => end.saturating_add(1),
+            Bound::Excluded(&end) => end,
+            Bound::Unbounded => self.layers.len(),
+        };
+        ensure!(
+            start <= end && end <= self.layers.len(),
+            "Layer range {start}..{end} out of bounds"
+        );
+
+        let removed = self.layers.drain(start..end).collect::<Vec<_>>();
+        let count = removed.len();
+
+        self.shift_layers(start, -self.header.layer_thickness * count as f32);
+
+        let bottom_removed = (start..start + count)
+            .filter(|&i| (i as u32) < self.header.bottom_layers)
+            .count();
+        self.header.bottom_layers -= bottom_removed as u32;
+        self.update_header();
+
+        Ok(removed)
+    }
+
+    /// Inserts a copy of a layer directly above it.
+    pub fn duplicate_layer(&mut self, index: usize) -> Result<()> {
+        let Some(layer) = self.layers.get(index).cloned() else {
+            bail!("Layer index {index} out of bounds");
+        };
+        self.insert_layer(index + 1, layer)
+    }
+
+    /// Rearranges the layers so that the layer at `order[i]` becomes layer `i`.
+    ///
+    /// Each layer takes the Z position of the slot it moves into. `order` must be a permutation of all layer indices.
+    pub fn reorder(&mut self, order: &[usize]) -> Result<()> {
+        ensure!(
+            order.len() == self.layers.len(),
+            "Expected {} layer indices, got {}",
+            self.layers.len(),
+            order.len()
+        );
+
+        let mut seen = vec![false; order.len()];
+        for &i in order {
+            ensure!(i < order.len(), "Layer index {i} out of bounds");
+            ensure!(!seen[i], "Layer index {i} appears more than once");
+            seen[i] = true;
+        }
+
+        let positions = self
+            .layers
+            .iter()
+            .map(|x| x.layer_position_z)
+            .collect::<Vec<_>>();
+        let mut layers = self.layers.drain(..).map(Some).collect::<Vec<_>>();
+
+        for (&i, position) in order.iter().zip(positions) {
+            let mut layer = layers[i].take().unwrap();
+            layer.layer_position_z = position;
+            self.layers.push(layer);
+        }
+
+        Ok(())
+    }
+
+    fn shift_layers(&mut self, from: usize, offset: f32) {
+        for layer in &mut self.layers[from..] {
+            layer.layer_position_z += offset;
+        }
+    }
+}
+
+/// Pauses.
+///
+/// Adding or clearing pauses updates the print time in the header.
+impl File {
+    /// Pauses the print before `layer`, parking the build plate at `z_park` mm.
+    pub fn add_pause(&mut self, layer: usize, z_park: f32) {
+        self.layers[layer].set_pause(Pause::Park { position_z: z_park });
+        self.update_header();
+    }
+
+    /// Removes all pauses from the print.
+    pub fn clear_pauses(&mut self) {
+        for layer in &mut self.layers {
+            layer.set_pause(Pause::None);
+        }
+        self.update_header();
+    }
+
+    /// Returns the index and park position of every paused layer.
+    pub fn pauses(&self) -> impl Iterator<Item = (usize, f32)> + '_ {
+        self.layers
+            .iter()
+            .enumerate()
+            .filter_map(|(i, layer)| match layer.pause() {
+                Pause::None => None,
+                Pause::Park { position_z } => Some((i, position_z)),
+            })
+    }
+
+    /// Estimate the total print time in seconds from the layer parameters.
+    pub fn print_time(&self) -> f32 {
+        self.layers.iter().map(LayerContent::print_time).sum()
+    }
+
+    /// Redraws the header previews as a top down view of every layer.
+    pub fn render_previews(&mut self) {
+        let (width, height) = self.resolution();
+        let mask = self.footprint();
+
+        self.header.small_preview = render_preview(&mask, width as usize, height as usize);
+        self.header.big_preview = render_preview(&mask, width as usize, height as usize);
+    }
+
+    /// Recalculates the layer count and print time in the header.
+    pub(crate) fn update_header(&mut self) {
+        self.header.layer_count = self.layers.len() as u32;
+        self.header.printing_time = self.print_time().round() as u32;
+    }
+}
+
+/// Splitting and salvaging prints.
+impl File {
+    /// Splits the print into one file per Z range, cutting after the last layer at or below each height in `z`.
+    ///
+    /// Each part keeps its absolute Z positions so it can continue printing on the same build plate as the part before it.
+    /// The original bottom layers stay bottom layers in whichever parts they end up in, so `bottom_layers` only counts the ones in each part.
+    /// Parts without any layers are skipped.
+    pub fn split_at(&self, z: &[f32]) -> Result<Vec<Self>> {
+        ensure!(
+            z.windows(2).all(|x| x[0] < x[1]),
+            "Split heights must be in ascending order"
+        );
+
+        let mut parts = Vec::new();
+        let mut start = 0;
+
+        for end in z
+            .iter()
+            .map(|&z| self.layers.partition_point(|x| x.layer_position_z <= z))
+            .chain([self.layers.len()])
+        {
+            let end = end.max(start);
+            if end == start {
+                continue;
+            }
+
+            let bottom_layers = self.header.bottom_layers.saturating_sub(start as u32);
+            let mut part = Self::new(
+                HeaderInfo {
+                    bottom_layers: bottom_layers.min((end - start) as u32),
+                    ..self.header.clone()
+                },
+                self.layers[start..end].to_vec(),
+            );
+            part.update_header();
+            part.render_previews();
+
+            parts.push(part);
+            start = end;
+        }
+
+        Ok(parts)
+    }
+
+    /// Splits the print into one file per object, so each can be printed on its own.
+    ///
+    /// Objects are the connected areas of the combined footprint of every layer, like in `arrange`.
+    /// Each part keeps the layer parameters and positions of the original, with empty layers past the top of its object removed.
+    pub fn split_objects(&self) -> Result<Vec<Self>> {
+        let (width, height) = self.resolution();
+        let (labels, objects) = label_objects(&self.footprint(), width, height);
+        ensure!(!objects.is_empty(), "File has no exposed pixels");
+
+        let mut parts = vec![(Vec::new(), 0); objects.len()];
+
+        for (i, layer) in self.layers.iter().enumerate() {
+            let source = layer.decode_pixels(width, height);
+            for (label, (layers, top)) in (1..).zip(&mut parts) {
+                let pixels = source
+                    .iter()
+                    .zip(&labels)
+                    .map(|(&value, &x)| if x == label { value } else { 0 })
+                    .collect::<Vec<_>>();
+
+                if pixels.iter().any(|&x| x > 0) {
+                    *top = i + 1;
+                }
+
+                let mut layer = layer.clone();
+                layer.set_pixels(width, height, &pixels);
+                layers.push(layer);
+            }
+        }
+
+        Ok(parts
+            .into_iter()
+            .map(|(mut layers, top)| {
+                layers.truncate(top);
+                let mut part = Self::new(
+                    HeaderInfo {
+                        bottom_layers: self.header.bottom_layers.min(top as u32),
+                        ..self.header.clone()
+                    },
+                    layers,
+                );
+                part.update_header();
+                part.render_previews();
+                part
+            })
+            .collect())
+    }
+
+    /// Creates a new file containing only the layers from `layer` onward, to continue a print that failed partway through.
+    ///
+    /// The remaining layers are moved down to start at the build plate, and the first `bottom_layers` of them are printed with the header's bottom layer parameters and `bottom_exposure_time` so they stick to the plate.
+    pub fn resume_from(
+        &self,
+        layer: usize,
+        bottom_layers: u32,
+        bottom_exposure_time: f32,
+    ) -> Result<Self> {
+        ensure!(
+            layer < self.layers.len(),
+            "Layer index {layer} out of bounds"
+        );
+
+        let header = &self.header;
+        let offset = self.layers[layer].layer_position_z - header.layer_thickness;
+        let layers = self.layers[layer..]
+            .iter()
+            .enumerate()
+            .map(|(i, layer)| {
+                let mut layer = layer.clone();
+                layer.layer_position_z -= offset;
+
+                if (i as u32) < bottom_layers {
+                    layer.layer_exposure_time = bottom_exposure_time;
+                    layer.before_lift_time = header.bottom_before_lift_time;
+                    layer.after_lift_time = header.bottom_after_lift_time;
+                    layer.after_retract_time = header.bottom_after_retract_time;
+                    layer.lift_distance = header.bottom_lift_distance;
+                    layer.lift_speed = header.bottom_lift_speed;
+                    layer.second_lift_distance = header.bottom_second_lift_distance;
+                    layer.second_lift_speed = header.bottom_second_lift_speed;
+                    layer.retract_distance = header.bottom_retract_distance;
+                    layer.retract_speed = header.bottom_retract_speed;
+                    layer.second_retract_distance = header.bottom_second_retract_distance;
+                    layer.second_retract_speed = header.bottom_second_retract_speed;
+                    layer.light_pwm = header.bottom_light_pwm;
+                }
+
+                layer
+            })
+            .collect::<Vec<_>>();
+
+        let mut out = Self::new(
+            HeaderInfo {
+                bottom_layers: bottom_layers.min(layers.len() as u32),
+                bottom_exposure_time,
+                ..header.clone()
+            },
+            layers,
+        );
+        out.update_header();
+        Ok(out)
+    }
+}
+
+/// Scales a mask of exposed pixels down to a preview, keeping its aspect ratio.
+/// Each preview pixel is shaded by how much of it is covered.
+fn render_preview<const WIDTH: usize, const HEIGHT: usize>(
+    mask: &[bool],
+    width: usize,
+    height: usize,
+) -> PreviewImage<WIDTH, HEIGHT> {
+    let mut out = PreviewImage::empty();
+    if width == 0 || height == 0 {
+        return out;
+    }
+
+    let scale = (width as f32 / WIDTH as f32).max(height as f32 / HEIGHT as f32);
+    let mut coverage = vec![0_u32; WIDTH * HEIGHT];
+
+    for (i, _) in mask.iter().enumerate().filter(|(_, &x)| x) {
+        let x = ((i % width) as f32 + 0.5 - width as f32 / 2.0) / scale + WIDTH as f32 / 2.0;
+        let y = ((i / width) as f32 + 0.5 - height as f32 / 2.0) / scale + HEIGHT as f32 / 2.0;
+        if (0.0..WIDTH as f32).contains(&x) && (0.0..HEIGHT as f32).contains(&y) {
+            coverage[y as usize * WIDTH + x as usize] += 1;
+        }
+    }
+
+    for (i, &count) in coverage.iter().enumerate() {
+        let value = (count as f32 / (scale * scale)).min(1.0);
+        out.set_pixel(i % WIDTH, i / WIDTH, (value, value, value));
+    }
+
+    out
+}
+
+impl SlicedFile for File {
+    fn info(&self) -> FileInfo {
+        FileInfo {
+            resolution: (
+                self.header.x_resolution as u32,
+                self.header.y_resolution as u32,
+            ),
+            size: (self.header.x_size, self.header.y_size, self.header.z_size),
+            layer_thickness: self.header.layer_thickness,
+            bottom_layers: self.header.bottom_layers,
+            exposure_time: self.header.exposure_time,
+            bottom_exposure_time: self.header.bottom_exposure_time,
+            print_time: self.header.printing_time,
+        }
+    }
+
+    fn layer_count(&self) -> usize {
+        self.layers.len()
+    }
+
+    fn layer_info(&self, layer: usize) -> LayerInfo {
+        let layer = &self.layers[layer];
+        LayerInfo {
+            position_z: layer.layer_position_z,
+            exposure_time: layer.layer_exposure_time,
+            lift_distance: layer.lift_distance + layer.second_lift_distance,
+            lift_speed: layer.lift_speed,
+            retract_distance: layer.retract_distance + layer.second_retract_distance,
+            retract_speed: layer.retract_speed,
+            light_pwm: layer.light_pwm,
+        }
+    }
+
+    fn decode_layer(&self, layer: usize) -> Result<Vec<u8>> {
+        let (width, height) = (
+            self.header.x_resolution as u32,
+            self.header.y_resolution as u32,
+        );
+        Ok(self.layers[layer].decode_pixels(width, height))
+    }
+}
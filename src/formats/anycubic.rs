@@ -0,0 +1,552 @@
+//! Anycubic Photon Workshop files (`.pws`, `.pwmx`, `.pwma` and friends).
+//!
+//! These files start with a file mark holding the offsets of the header, preview, layer definition and layer image sections.
+//! Every section starts with a 12 byte name and its length.
+//! Version 1 (`.pws`) stores each layer as one 1-bit bitmap per anti-aliasing level, while later versions (`.pwmx` and others) store 4-bit grayscale runs.
+//!
+//! Speeds are stored in mm/s, they are converted to the mm/min used by goo files.
+
+use anyhow::{ensure, Result};
+
+use super::{
+    le::{Reader, Writer},
+    pixel_count, Preview,
+};
+use crate::{
+    encoded_layer::LayerDecoder,
+    file::File as GooFile,
+    header_info::HeaderInfo,
+    layer_content::LayerContent,
+    misc::{FileInfo, LayerInfo, Run, SlicedFile},
+};
+
+const MARK: &[u8; 12] = b"ANYCUBIC\0\0\0\0";
+const FILE_MARK_SIZE: usize = 48;
+const LAYER_DEF_SIZE: u32 = 32;
+const HEADER_SIZE: u32 = 80;
+
+const PREVIEW_WIDTH: u32 = 224;
+const PREVIEW_HEIGHT: u32 = 168;
+
+/// The version used by the original `.pws` format, with 1-bit layers.
+pub const VERSION_PWS: u32 = 1;
+/// The version used by `.pwmx` files, with 4-bit grayscale layers.
+pub const VERSION_PWMX: u32 = 515;
+
+/// An Anycubic Photon Workshop file.
+pub struct File {
+    pub version: u32,
+    pub header: Header,
+    pub preview: Preview,
+    pub layers: Vec<Layer>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Header {
+    /// Size of a pixel in micrometers.
+    pub pixel_size: f32,
+    pub layer_height: f32,
+    pub exposure_time: f32,
+    pub wait_time_before_cure: f32,
+    pub bottom_exposure_time: f32,
+    pub bottom_layers: u32,
+    pub lift_height: f32,
+    /// Lift speed in mm/s.
+    pub lift_speed: f32,
+    /// Retract speed in mm/s.
+    pub retract_speed: f32,
+    pub volume: f32,
+    pub anti_aliasing: u32,
+    pub resolution: [u32; 2],
+    pub weight: f32,
+    pub price: f32,
+    pub price_currency: u32,
+    pub per_layer_override: bool,
+    /// Estimated print time in seconds.
+    pub print_time: u32,
+    pub transition_layers: u32,
+}
+
+#[derive(Clone, Debug)]
+pub struct Layer {
+    pub lift_height: f32,
+    /// Lift speed in mm/s.
+    pub lift_speed: f32,
+    pub exposure_time: f32,
+    /// Thickness of this layer.
+    pub layer_height: f32,
+    /// Z position of this layer, the sum of its own and all previous layer heights.
+    /// It isn't stored in the file, it is computed once when reading or converting.
+    pub position_z: f32,
+    /// The run length encoded layer image.
+    pub data: Vec<u8>,
+}
+
+impl File {
+    pub fn resolution(&self) -> (u32, u32) {
+        (self.header.resolution[0], self.header.resolution[1])
+    }
+
+    /// Height of the print in millimeters, the Z position of the last layer.
+    pub fn height(&self) -> f32 {
+        self.layers.last().map_or(0.0, |x| x.position_z)
+    }
+
+    /// Decodes a layer into a flat `Vec<u8>` of grayscale pixels.
+    pub fn decode_pixels(&self, layer: usize) -> Vec<u8> {
+        let (width, height) = self.resolution();
+        let pixels = width as usize * height as usize;
+        let data = &self.layers[layer].data;
+
+        if self.version == VERSION_PWS {
+            let levels = self.header.anti_aliasing.max(1);
+            let mut counts = vec![0u32; pixels];
+            decode_pws(data, &mut counts);
+            return counts
+                .iter()
+                .map(|&x| (x.min(levels) as u64 * 255 / levels as u64) as u8)
+                .collect();
+        }
+
+        let mut out = Vec::with_capacity(pixels);
+        for Run { length, value } in decode_pw0(data) {
+            let length = (length as usize).min(pixels - out.len());
+            out.extend(std::iter::repeat_n(value, length));
+        }
+        out.resize(pixels, 0);
+        out
+    }
+}
+
+impl File {
+    pub fn deserialize(buf: &[u8]) -> Result<Self> {
+        let mut des = Reader::new(buf);
+
+        ensure!(des.read_bytes(12)? == MARK, "Invalid file mark");
+        let version = des.read_u32()?;
+        let _area_count = des.read_u32()?;
+        let header_offset = des.read_u32()?;
+        des.read_u32()?;
+        let preview_offset = des.read_u32()?;
+        des.read_u32()?;
+        let layer_def_offset = des.read_u32()?;
+
+        des.seek(header_offset as usize);
+        read_section(&mut des, "HEADER")?;
+        let header = Header {
+            pixel_size: des.read_f32()?,
+            layer_height: des.read_f32()?,
+            exposure_time: des.read_f32()?,
+            wait_time_before_cure: des.read_f32()?,
+            bottom_exposure_time: des.read_f32()?,
+            bottom_layers: des.read_f32()? as u32,
+            lift_height: des.read_f32()?,
+            lift_speed: des.read_f32()?,
+            retract_speed: des.read_f32()?,
+            volume: des.read_f32()?,
+            anti_aliasing: des.read_u32()?,
+            resolution: [des.read_u32()?, des.read_u32()?],
+            weight: des.read_f32()?,
+            price: des.read_f32()?,
+            price_currency: des.read_u32()?,
+            per_layer_override: des.read_u32()? != 0,
+            print_time: des.read_u32()?,
+            transition_layers: des.read_u32()?,
+        };
+
+        pixel_count(header.resolution[0], header.resolution[1])?;
+
+        des.seek(preview_offset as usize);
+        read_section(&mut des, "PREVIEW")?;
+        let width = des.read_u32()?;
+        let _mark = des.read_u32()?;
+        let height = des.read_u32()?;
+        pixel_count(width, height)?;
+        let mut preview = Preview::empty(width, height);
+        for pixel in preview.data.iter_mut() {
+            *pixel = des.read_u16()?;
+        }
+
+        des.seek(layer_def_offset as usize);
+        read_section(&mut des, "LAYERDEF")?;
+        let layer_count = des.read_u32()?;
+
+        // Every layer needs a definition, so don't trust a layer count the file can't hold
+        let capacity = (layer_count as usize).min(buf.len() / LAYER_DEF_SIZE as usize);
+        let mut position_z = 0.0;
+        let mut layers = Vec::with_capacity(capacity);
+        for i in 0..layer_count {
+            des.seek(layer_def_offset as usize + 20 + i as usize * LAYER_DEF_SIZE as usize);
+            let data_offset = des.read_u32()?;
+            let data_size = des.read_u32()?;
+            let lift_height = des.read_f32()?;
+            let lift_speed = des.read_f32()?;
+            let exposure_time = des.read_f32()?;
+            let layer_height = des.read_f32()?;
+            position_z += layer_height;
+
+            des.seek(data_offset as usize);
+            layers.push(Layer {
+                lift_height,
+                lift_speed,
+                exposure_time,
+                layer_height,
+                position_z,
+                data: des.read_bytes(data_size as usize)?.to_vec(),
+            });
+        }
+
+        Ok(Self {
+            version,
+            header,
+            preview,
+            layers,
+        })
+    }
+
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut ser = Writer::new();
+        ser.write_bytes(&[0; FILE_MARK_SIZE]);
+
+        let header_offset = ser.pos() as u32;
+        let header = &self.header;
+        write_section(&mut ser, "HEADER", HEADER_SIZE);
+        ser.write_f32(header.pixel_size);
+        ser.write_f32(header.layer_height);
+        ser.write_f32(header.exposure_time);
+        ser.write_f32(header.wait_time_before_cure);
+        ser.write_f32(header.bottom_exposure_time);
+        ser.write_f32(header.bottom_layers as f32);
+        ser.write_f32(header.lift_height);
+        ser.write_f32(header.lift_speed);
+        ser.write_f32(header.retract_speed);
+        ser.write_f32(header.volume);
+        ser.write_u32(header.anti_aliasing);
+        ser.write_u32(header.resolution[0]);
+        ser.write_u32(header.resolution[1]);
+        ser.write_f32(header.weight);
+        ser.write_f32(header.price);
+        ser.write_u32(header.price_currency);
+        ser.write_u32(header.per_layer_override as u32);
+        ser.write_u32(header.print_time);
+        ser.write_u32(header.transition_layers);
+        ser.write_u32(0);
+
+        let preview_offset = ser.pos() as u32;
+        write_section(&mut ser, "PREVIEW", 12 + self.preview.data.len() as u32 * 2);
+        ser.write_u32(self.preview.width);
+        ser.write_u32(b'x' as u32);
+        ser.write_u32(self.preview.height);
+        for &pixel in &self.preview.data {
+            ser.write_u16(pixel);
+        }
+
+        let layer_def_offset = ser.pos() as u32;
+        let layer_count = self.layers.len() as u32;
+        write_section(&mut ser, "LAYERDEF", 4 + layer_count * LAYER_DEF_SIZE);
+        ser.write_u32(layer_count);
+
+        let layer_image_offset = ser.pos() as u32 + layer_count * LAYER_DEF_SIZE;
+        let mut data_offset = layer_image_offset;
+        for layer in &self.layers {
+            ser.write_u32(data_offset);
+            ser.write_u32(layer.data.len() as u32);
+            ser.write_f32(layer.lift_height);
+            ser.write_f32(layer.lift_speed);
+            ser.write_f32(layer.exposure_time);
+            ser.write_f32(layer.layer_height);
+            ser.write_u32(self.count_exposed(layer));
+            ser.write_u32(0);
+            data_offset += layer.data.len() as u32;
+        }
+
+        for layer in &self.layers {
+            ser.write_bytes(&layer.data);
+        }
+
+        let mut mark = Writer::new();
+        mark.write_bytes(MARK);
+        mark.write_u32(self.version);
+        mark.write_u32(4);
+        for offset in [header_offset, preview_offset, layer_def_offset] {
+            mark.write_u32(offset);
+            mark.write_u32(0);
+        }
+        mark.write_u32(layer_image_offset);
+        ser.patch_bytes(0, &mark.into_inner());
+
+        ser.into_inner()
+    }
+
+    fn count_exposed(&self, layer: &Layer) -> u32 {
+        if self.version == VERSION_PWS {
+            return 0;
+        }
+
+        decode_pw0(&layer.data)
+            .filter(|x| x.value != 0)
+            .map(|x| x.length as u32)
+            .sum()
+    }
+}
+
+impl File {
+    /// Converts a `GooFile` into a `.pwmx` file, or a `.pws` file if `version` is [`VERSION_PWS`].
+    ///
+    /// Grayscale pixels are reduced to 4 bits for `.pwmx` files, or to one bitmap per anti-aliasing level of the goo file for `.pws` files.
+    pub fn from_goo(goo: &GooFile, version: u32) -> Self {
+        let header = &goo.header;
+        let (width, height) = (header.x_resolution as u32, header.y_resolution as u32);
+        let anti_aliasing = header.anti_aliasing_level.max(1) as u32;
+
+        let mut last_z = 0.0;
+        let layers = goo
+            .layers
+            .iter()
+            .map(|layer| {
+                let data = if version == VERSION_PWS {
+                    let pixels = layer.decode_pixels(width, height);
+                    (0..anti_aliasing)
+                        .flat_map(|level| encode_pws(&pixels, level, anti_aliasing))
+                        .collect()
+                } else {
+                    encode_pw0(LayerDecoder::new(&layer.data))
+                };
+
+                let layer_height = layer.layer_position_z - last_z;
+                last_z = layer.layer_position_z;
+
+                Layer {
+                    lift_height: layer.lift_distance,
+                    lift_speed: layer.lift_speed / 60.0,
+                    exposure_time: layer.layer_exposure_time,
+                    layer_height,
+                    position_z: layer.layer_position_z,
+                    data,
+                }
+            })
+            .collect();
+
+        Self {
+            version,
+            header: Header {
+                pixel_size: header.x_size / width as f32 * 1000.0,
+                layer_height: header.layer_thickness,
+                exposure_time: header.exposure_time,
+                wait_time_before_cure: header.turn_off_time,
+                bottom_exposure_time: header.bottom_exposure_time,
+                bottom_layers: header.bottom_layers,
+                lift_height: header.lift_distance,
+                lift_speed: header.lift_speed / 60.0,
+                retract_speed: header.retract_speed / 60.0,
+                volume: header.total_volume,
+                anti_aliasing,
+                resolution: [width, height],
+                weight: header.total_weight,
+                price: header.total_price,
+                price_currency: 0,
+                per_layer_override: header.advance_mode,
+                print_time: header.printing_time,
+                transition_layers: header.transition_layers as u32,
+            },
+            preview: Preview::from_goo(&header.big_preview).resize(PREVIEW_WIDTH, PREVIEW_HEIGHT),
+            layers,
+        }
+    }
+
+    /// Converts this file into a `GooFile`.
+    pub fn to_goo(&self) -> GooFile {
+        let header = &self.header;
+        let (width, height) = self.resolution();
+
+        let mut layers = Vec::with_capacity(self.layers.len());
+        for (i, layer) in self.layers.iter().enumerate() {
+            let mut content = LayerContent {
+                layer_position_z: layer.position_z,
+                layer_exposure_time: layer.exposure_time,
+                layer_off_time: header.wait_time_before_cure,
+                lift_distance: layer.lift_height,
+                lift_speed: layer.lift_speed * 60.0,
+                retract_distance: layer.lift_height,
+                retract_speed: header.retract_speed * 60.0,
+                ..Default::default()
+            };
+            content.set_pixels(width, height, &self.decode_pixels(i));
+            layers.push(content);
+        }
+
+        let size = |pixels: u32| pixels as f32 * header.pixel_size / 1000.0;
+        GooFile::new(
+            HeaderInfo {
+                anti_aliasing_level: header.anti_aliasing as u16,
+                small_preview: self.preview.to_goo(),
+                big_preview: self.preview.to_goo(),
+                layer_count: self.layers.len() as u32,
+                x_resolution: width as u16,
+                y_resolution: height as u16,
+                x_size: size(width),
+                y_size: size(height),
+                layer_thickness: header.layer_height,
+                exposure_time: header.exposure_time,
+                turn_off_time: header.wait_time_before_cure,
+                bottom_exposure_time: header.bottom_exposure_time,
+                bottom_layers: header.bottom_layers,
+                bottom_lift_distance: header.lift_height,
+                bottom_lift_speed: header.lift_speed * 60.0,
+                lift_distance: header.lift_height,
+                lift_speed: header.lift_speed * 60.0,
+                bottom_retract_distance: header.lift_height,
+                bottom_retract_speed: header.retract_speed * 60.0,
+                retract_distance: header.lift_height,
+                retract_speed: header.retract_speed * 60.0,
+                advance_mode: header.per_layer_override,
+                printing_time: header.print_time,
+                total_volume: header.volume,
+                total_weight: header.weight,
+                total_price: header.price,
+                transition_layers: header.transition_layers as u16,
+                ..Default::default()
+            },
+            layers,
+        )
+    }
+}
+
+impl SlicedFile for File {
+    fn info(&self) -> FileInfo {
+        let (width, height) = self.resolution();
+        let size = |pixels: u32| pixels as f32 * self.header.pixel_size / 1000.0;
+        FileInfo {
+            resolution: (width, height),
+            // The build height isn't stored, so this reports the height of the print
+            size: (size(width), size(height), self.height()),
+            layer_thickness: self.header.layer_height,
+            bottom_layers: self.header.bottom_layers,
+            exposure_time: self.header.exposure_time,
+            bottom_exposure_time: self.header.bottom_exposure_time,
+            print_time: self.header.print_time,
+        }
+    }
+
+    fn layer_count(&self) -> usize {
+        self.layers.len()
+    }
+
+    fn layer_info(&self, layer: usize) -> LayerInfo {
+        let layer = &self.layers[layer];
+        LayerInfo {
+            position_z: layer.position_z,
+            exposure_time: layer.exposure_time,
+            lift_distance: layer.lift_height,
+            lift_speed: layer.lift_speed * 60.0,
+            retract_distance: layer.lift_height,
+            retract_speed: self.header.retract_speed * 60.0,
+            light_pwm: 255,
+        }
+    }
+
+    fn decode_layer(&self, layer: usize) -> Result<Vec<u8>> {
+        Ok(self.decode_pixels(layer))
+    }
+}
+
+fn read_section(des: &mut Reader, name: &str) -> Result<u32> {
+    let mark = des.read_bytes(12)?;
+    let end = mark.iter().position(|&x| x == 0).unwrap_or(12);
+    ensure!(&mark[..end] == name.as_bytes(), "Expected {name} section");
+    des.read_u32()
+}
+
+fn write_section(ser: &mut Writer, name: &str, length: u32) {
+    let mut mark = [0; 12];
+    mark[..name.len()].copy_from_slice(name.as_bytes());
+    ser.write_bytes(&mark);
+    ser.write_u32(length);
+}
+
+/// Decodes the 4-bit grayscale runs used by version 515 and later.
+fn decode_pw0(data: &[u8]) -> impl Iterator<Item = Run> + '_ {
+    let mut i = 0;
+    std::iter::from_fn(move || {
+        let head = *data.get(i)?;
+        let code = head >> 4;
+        let mut length = (head & 0x0F) as u64;
+        i += 1;
+
+        if code == 0x0 || code == 0xF {
+            length = (length << 8) | *data.get(i)? as u64;
+            i += 1;
+        }
+
+        Some(Run {
+            length,
+            value: code * 0x11,
+        })
+    })
+}
+
+/// Encodes runs into the 4-bit grayscale format used by version 515 and later.
+fn encode_pw0(runs: impl Iterator<Item = Run>) -> Vec<u8> {
+    let mut out = Vec::new();
+    let mut runs = runs.filter(|x| x.length > 0).peekable();
+
+    while let Some(Run { mut length, value }) = runs.next() {
+        let code = value >> 4;
+        while let Some(run) = runs.next_if(|x| x.value >> 4 == code) {
+            length += run.length;
+        }
+
+        let max = if code == 0x0 || code == 0xF {
+            0xFFF
+        } else {
+            0xF
+        };
+        while length > 0 {
+            let run = length.min(max);
+            length -= run;
+
+            if max == 0xF {
+                out.push((code << 4) | run as u8);
+            } else {
+                out.extend_from_slice(&[(code << 4) | (run >> 8) as u8, run as u8]);
+            }
+        }
+    }
+
+    out
+}
+
+/// Decodes the 1-bit bitmaps used by version 1, one after the other for each anti-aliasing level.
+fn decode_pws(data: &[u8], counts: &mut [u32]) {
+    let mut pixel = 0;
+    for &byte in data {
+        let length = (byte & 0x7F) as usize + 1;
+        if byte & 0x80 != 0 {
+            for i in pixel..pixel + length {
+                if let Some(count) = counts.get_mut(i % counts.len().max(1)) {
+                    *count += 1;
+                }
+            }
+        }
+        pixel += length;
+    }
+}
+
+/// Encodes the pixels that are set for `level` out of `levels` anti-aliasing levels into a version 1 bitmap.
+fn encode_pws(pixels: &[u8], level: u32, levels: u32) -> Vec<u8> {
+    let mut out = Vec::new();
+    let mut pixels = pixels
+        .iter()
+        .map(|&x| (x as u32 * levels + 127) / 255 > level)
+        .peekable();
+
+    while let Some(set) = pixels.next() {
+        let mut length = 1;
+        while length < 0x7D && pixels.next_if_eq(&set).is_some() {
+            length += 1;
+        }
+        out.push(((set as u8) << 7) | (length - 1));
+    }
+
+    out
+}
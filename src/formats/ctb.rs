@@ -0,0 +1,1251 @@
+//! Chitubox `.ctb` and `.cbddlp` files.
+//!
+//! These files start with a fixed size header holding the offsets of all other sections: the two previews, the print parameters, the slicer info, the layer definition table and the layer images.
+//! Layers in `.ctb` files are stored as 7-bit grayscale runs, while `.cbddlp` files store one 1-bit bitmap per anti-aliasing level.
+//! Starting with version 3, each layer image is preceded by its own lift and wait parameters.
+//!
+//! Layer data can be encrypted with a simple XOR scheme keyed by `Header::encryption_key`.
+//! This is handled transparently when reading and writing, so `Layer::data` is always the plain run length encoded image.
+//! The AES encrypted variant of CTBv4 (magic `0x12FD0107`) is rejected with an error, as decrypting its settings block needs the Chitubox key.
+
+use anyhow::{bail, ensure, Result};
+
+use super::{
+    le::{Reader, Writer},
+    pixel_count, Preview,
+};
+use crate::{
+    encoded_layer::{LayerDecoder, LayerEncoder},
+    file::File as GooFile,
+    header_info::HeaderInfo,
+    layer_content::LayerContent,
+    misc::{FileInfo, LayerInfo, Run, SlicedFile},
+    serde::SizedString,
+};
+
+const MAGIC_CBDDLP: u32 = 0x12FD0019;
+const MAGIC_CTB: u32 = 0x12FD0086;
+const MAGIC_CTB_ENCRYPTED: u32 = 0x12FD0107;
+
+const HEADER_SIZE: usize = 112;
+const LAYER_DEF_SIZE: u32 = 36;
+const LAYER_DEF_EX_SIZE: u32 = 48;
+const PRINT_PARAMETERS_SIZE: u32 = 60;
+const PRINT_PARAMETERS_V4_RESERVED: usize = 384;
+
+const PER_LAYER_SETTINGS: u8 = 0x40;
+const SOFTWARE_VERSION: u32 = 0x01090000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Kind {
+    Ctb,
+    Cbddlp,
+}
+
+/// A Chitubox `.ctb` or `.cbddlp` file.
+pub struct File {
+    pub kind: Kind,
+    pub version: u32,
+    pub header: Header,
+    pub print_parameters: PrintParameters,
+    pub slicer_info: SlicerInfo,
+    /// Only stored in version 4 and later.
+    pub print_parameters_v4: PrintParametersV4,
+    pub machine_name: String,
+    pub large_preview: Preview,
+    pub small_preview: Preview,
+    pub layers: Vec<Layer>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Header {
+    pub bed_size: [f32; 3],
+    pub total_height: f32,
+    pub layer_height: f32,
+    pub exposure_time: f32,
+    pub bottom_exposure_time: f32,
+    pub light_off_delay: f32,
+    pub bottom_layers: u32,
+    pub resolution: [u32; 2],
+    /// Estimated print time in seconds.
+    pub print_time: u32,
+    pub mirrored: bool,
+    pub anti_alias_level: u32,
+    pub light_pwm: u16,
+    pub bottom_light_pwm: u16,
+    /// Key used to encrypt the layer data, zero if it is not encrypted.
+    pub encryption_key: u32,
+}
+
+#[derive(Clone, Debug)]
+pub struct PrintParameters {
+    pub bottom_lift_height: f32,
+    pub bottom_lift_speed: f32,
+    pub lift_height: f32,
+    pub lift_speed: f32,
+    pub retract_speed: f32,
+    pub volume: f32,
+    pub weight: f32,
+    pub cost: f32,
+    pub bottom_light_off_delay: f32,
+    pub light_off_delay: f32,
+    pub bottom_layers: u32,
+}
+
+#[derive(Clone, Debug)]
+pub struct SlicerInfo {
+    pub bottom_lift_height_2: f32,
+    pub bottom_lift_speed_2: f32,
+    pub lift_height_2: f32,
+    pub lift_speed_2: f32,
+    pub retract_height_2: f32,
+    pub retract_speed_2: f32,
+    pub rest_time_after_lift: f32,
+    /// Raw flag enabling the per-layer parameters, zero if disabled.
+    pub per_layer_settings: u8,
+    pub timestamp_minutes: u32,
+    pub anti_alias_level: u32,
+    pub software_version: u32,
+    pub rest_time_after_retract: f32,
+    pub rest_time_after_lift_2: f32,
+    pub transition_layers: u32,
+}
+
+#[derive(Clone, Debug)]
+pub struct PrintParametersV4 {
+    pub bottom_retract_speed: f32,
+    pub bottom_retract_speed_2: f32,
+    pub rest_time_after_retract: f32,
+    pub rest_time_after_lift: f32,
+    pub rest_time_before_lift: f32,
+    pub bottom_retract_height_2: f32,
+}
+
+/// A layer definition and, for version 3 and later, its extended parameters.
+///
+/// Retracting first moves `lift_height + lift_height_2 - retract_height_2` at `retract_speed`, then `retract_height_2` at `retract_speed_2`.
+#[derive(Clone, Debug)]
+pub struct Layer {
+    pub position_z: f32,
+    pub exposure_time: f32,
+    pub light_off_delay: f32,
+    pub lift_height: f32,
+    pub lift_speed: f32,
+    pub lift_height_2: f32,
+    pub lift_speed_2: f32,
+    pub retract_speed: f32,
+    pub retract_height_2: f32,
+    pub retract_speed_2: f32,
+    pub rest_time_before_lift: f32,
+    pub rest_time_after_lift: f32,
+    pub rest_time_after_retract: f32,
+    pub light_pwm: u16,
+    /// The unencrypted layer image.
+    /// For `.ctb` files this holds a single grayscale image, for `.cbddlp` files there is one bitmap per anti-aliasing level.
+    pub data: Vec<Vec<u8>>,
+}
+
+impl File {
+    pub fn resolution(&self) -> (u32, u32) {
+        (self.header.resolution[0], self.header.resolution[1])
+    }
+
+    /// Decodes a layer into a flat `Vec<u8>` of grayscale pixels.
+    pub fn decode_pixels(&self, layer: usize) -> Result<Vec<u8>> {
+        let (width, height) = self.resolution();
+        let layer = &self.layers[layer];
+        let pixels = pixel_count(width, height)?;
+
+        match self.kind {
+            Kind::Ctb => {
+                let mut out = Vec::with_capacity(pixels);
+                for Run { length, value } in decode_ctb(&layer.data[0])? {
+                    let length = (length as usize).min(pixels - out.len());
+                    out.extend(std::iter::repeat_n(value, length));
+                }
+                out.resize(pixels, 0);
+                Ok(out)
+            }
+            Kind::Cbddlp => {
+                let mut counts = vec![0u32; pixels];
+                for level in &layer.data {
+                    decode_cbddlp(level, &mut counts);
+                }
+
+                let levels = layer.data.len().max(1) as u32;
+                Ok(counts.iter().map(|&x| (x * 255 / levels) as u8).collect())
+            }
+        }
+    }
+}
+
+impl File {
+    pub fn deserialize(buf: &[u8]) -> Result<Self> {
+        let mut des = Reader::new(buf);
+
+        let kind = match des.read_u32()? {
+            MAGIC_CTB => Kind::Ctb,
+            MAGIC_CBDDLP => Kind::Cbddlp,
+            MAGIC_CTB_ENCRYPTED => bail!(
+                "Encrypted CTBv4 files (magic {MAGIC_CTB_ENCRYPTED:#x}) are not supported yet, \
+                 their AES encrypted settings block needs the Chitubox key"
+            ),
+            magic => bail!("Invalid magic number {magic:#x}"),
+        };
+        let version = des.read_u32()?;
+
+        let bed_size = [des.read_f32()?, des.read_f32()?, des.read_f32()?];
+        des.read_bytes(8)?;
+        let total_height = des.read_f32()?;
+        let layer_height = des.read_f32()?;
+        let exposure_time = des.read_f32()?;
+        let bottom_exposure_time = des.read_f32()?;
+        let light_off_delay = des.read_f32()?;
+        let bottom_layers = des.read_u32()?;
+        let resolution = [des.read_u32()?, des.read_u32()?];
+        let large_preview_offset = des.read_u32()?;
+        let layer_table_offset = des.read_u32()?;
+        let layer_count = des.read_u32()?;
+        let small_preview_offset = des.read_u32()?;
+        let print_time = des.read_u32()?;
+        let mirrored = des.read_u32()? != 0;
+        let print_parameters_offset = des.read_u32()?;
+        let _print_parameters_size = des.read_u32()?;
+        let anti_alias_level = des.read_u32()?.max(1);
+        let light_pwm = des.read_u16()?;
+        let bottom_light_pwm = des.read_u16()?;
+        let encryption_key = match kind {
+            Kind::Ctb => des.read_u32()?,
+            Kind::Cbddlp => 0,
+        };
+        des.seek(HEADER_SIZE - 8);
+        let slicer_info_offset = des.read_u32()?;
+
+        pixel_count(resolution[0], resolution[1])?;
+        if kind == Kind::Cbddlp {
+            ensure!(
+                anti_alias_level <= 16,
+                "Invalid anti-aliasing level {anti_alias_level}"
+            );
+        }
+
+        let header = Header {
+            bed_size,
+            total_height,
+            layer_height,
+            exposure_time,
+            bottom_exposure_time,
+            light_off_delay,
+            bottom_layers,
+            resolution,
+            print_time,
+            mirrored,
+            anti_alias_level,
+            light_pwm,
+            bottom_light_pwm,
+            encryption_key,
+        };
+
+        let large_preview = read_preview(&mut des, large_preview_offset)?;
+        let small_preview = read_preview(&mut des, small_preview_offset)?;
+
+        let print_parameters = if print_parameters_offset != 0 {
+            des.seek(print_parameters_offset as usize);
+            PrintParameters::deserialize(&mut des)?
+        } else {
+            PrintParameters::from_header(&header)
+        };
+
+        let mut machine_name = String::new();
+        let mut print_parameters_v4_offset = 0;
+        let slicer_info = if slicer_info_offset != 0 {
+            des.seek(slicer_info_offset as usize);
+            let (slicer_info, name_offset, name_size, v4_offset) =
+                SlicerInfo::deserialize(&mut des)?;
+
+            des.seek(name_offset as usize);
+            let name = des.read_bytes(name_size as usize)?;
+            machine_name = String::from_utf8_lossy(name).into_owned();
+            print_parameters_v4_offset = v4_offset;
+            slicer_info
+        } else {
+            SlicerInfo::default()
+        };
+
+        let print_parameters_v4 = if version >= 4 && print_parameters_v4_offset != 0 {
+            des.seek(print_parameters_v4_offset as usize);
+            PrintParametersV4::deserialize(&mut des)?
+        } else {
+            PrintParametersV4::from_parameters(&print_parameters, &slicer_info)
+        };
+
+        let levels = match kind {
+            Kind::Ctb => 1,
+            Kind::Cbddlp => anti_alias_level,
+        };
+
+        // Every layer needs a definition, so don't trust a layer count the file can't hold
+        let capacity = (layer_count as usize).min(buf.len() / LAYER_DEF_SIZE as usize);
+        let mut layers = Vec::<Layer>::with_capacity(capacity);
+        for level in 0..levels {
+            for i in 0..layer_count {
+                let offset = (level as u64 * layer_count as u64 + i as u64)
+                    .checked_mul(LAYER_DEF_SIZE as u64)
+                    .and_then(|x| x.checked_add(layer_table_offset as u64))
+                    .and_then(|x| usize::try_from(x).ok());
+                let Some(offset) = offset else {
+                    bail!("Layer {i} definition is outside the file");
+                };
+                des.seek(offset);
+
+                let position_z = des.read_f32()?;
+                let exposure_time = des.read_f32()?;
+                let light_off_delay = des.read_f32()?;
+                let data_address = des.read_u32()? as usize;
+                let data_size = des.read_u32()? as usize;
+                let page = des.read_u32()? as usize;
+                let data_address = data_address | (page << 32);
+
+                des.seek(data_address);
+                let mut data = des.read_bytes(data_size)?.to_vec();
+
+                if level > 0 {
+                    layers[i as usize].data.push(data);
+                    continue;
+                }
+
+                if encryption_key != 0 {
+                    crypt_layer(encryption_key, i, &mut data);
+                }
+
+                let mut layer = Layer::from_globals(
+                    &header,
+                    &print_parameters,
+                    &slicer_info,
+                    &print_parameters_v4,
+                    i,
+                );
+                layer.position_z = position_z;
+                layer.exposure_time = exposure_time;
+                layer.light_off_delay = light_off_delay;
+                layer.data = vec![data];
+
+                if kind == Kind::Ctb && version >= 3 {
+                    let Some(extended) = data_address.checked_sub(LAYER_DEF_EX_SIZE as usize)
+                    else {
+                        bail!("Layer {i} data starts before its extended parameters");
+                    };
+                    des.seek(extended);
+                    layer.deserialize_extended(&mut des)?;
+                }
+
+                layers.push(layer);
+            }
+        }
+
+        Ok(Self {
+            kind,
+            version,
+            header,
+            print_parameters,
+            slicer_info,
+            print_parameters_v4,
+            machine_name,
+            large_preview,
+            small_preview,
+            layers,
+        })
+    }
+
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut ser = Writer::new();
+        ser.write_bytes(&[0; HEADER_SIZE]);
+
+        let large_preview_offset = write_preview(&mut ser, &self.large_preview);
+        let small_preview_offset = write_preview(&mut ser, &self.small_preview);
+
+        let print_parameters_offset = ser.pos() as u32;
+        self.print_parameters.serialize(&mut ser);
+
+        let slicer_info_offset = ser.pos() as u32;
+        let print_parameters_v4_patch = self.slicer_info.serialize(
+            &mut ser,
+            self.machine_name.as_bytes(),
+            self.header.anti_alias_level,
+        );
+        ser.write_bytes(self.machine_name.as_bytes());
+
+        if self.version >= 4 {
+            let offset = ser.pos() as u32;
+            ser.patch_u32(print_parameters_v4_patch, offset);
+            self.print_parameters_v4
+                .serialize(&mut ser, self.layers.len() as u32);
+        }
+
+        let levels = match self.kind {
+            Kind::Ctb => 1,
+            Kind::Cbddlp => self.header.anti_alias_level.max(1),
+        };
+        let layer_count = self.layers.len() as u32;
+        let layer_table_offset = ser.pos() as u32;
+        ser.write_bytes(&vec![0; (levels * layer_count * LAYER_DEF_SIZE) as usize]);
+
+        for level in 0..levels {
+            for (i, layer) in self.layers.iter().enumerate() {
+                let mut data = layer.data.get(level as usize).cloned().unwrap_or_default();
+                let extended = self.kind == Kind::Ctb && self.version >= 3;
+
+                if self.header.encryption_key != 0 && self.kind == Kind::Ctb {
+                    crypt_layer(self.header.encryption_key, i as u32, &mut data);
+                }
+
+                if extended {
+                    let start = ser.pos() + (LAYER_DEF_SIZE + LAYER_DEF_EX_SIZE) as usize;
+                    layer.serialize_definition(&mut ser, start, data.len());
+                    layer.serialize_extended(&mut ser);
+                }
+
+                let mut definition = Writer::new();
+                layer.serialize_definition(&mut definition, ser.pos(), data.len());
+                let offset = layer_table_offset + (level * layer_count + i as u32) * LAYER_DEF_SIZE;
+                ser.patch_bytes(offset as usize, &definition.into_inner());
+
+                ser.write_bytes(&data);
+            }
+        }
+
+        let mut header = Writer::new();
+        self.header.serialize(
+            &mut header,
+            self,
+            Offsets {
+                large_preview: large_preview_offset,
+                small_preview: small_preview_offset,
+                print_parameters: print_parameters_offset,
+                slicer_info: slicer_info_offset,
+                layer_table: layer_table_offset,
+            },
+        );
+        ser.patch_bytes(0, &header.into_inner());
+
+        ser.into_inner()
+    }
+}
+
+impl File {
+    /// Converts a `GooFile` into a version 4 `.ctb` file or a version 2 `.cbddlp` file.
+    ///
+    /// Grayscale pixels are reduced to 7 bits for `.ctb` files, or to one bitmap per anti-aliasing level of the goo file for `.cbddlp` files.
+    pub fn from_goo(goo: &GooFile, kind: Kind) -> Self {
+        let header = &goo.header;
+        let (width, height) = (header.x_resolution as u32, header.y_resolution as u32);
+        let anti_alias_level = header.anti_aliasing_level.max(1) as u32;
+
+        let layers = goo
+            .layers
+            .iter()
+            .map(|layer| Layer {
+                position_z: layer.layer_position_z,
+                exposure_time: layer.layer_exposure_time,
+                light_off_delay: layer.layer_off_time,
+                lift_height: layer.lift_distance,
+                lift_speed: layer.lift_speed,
+                lift_height_2: layer.second_lift_distance,
+                lift_speed_2: layer.second_lift_speed,
+                retract_speed: layer.retract_speed,
+                retract_height_2: layer.second_retract_distance,
+                retract_speed_2: layer.second_retract_speed,
+                rest_time_before_lift: layer.before_lift_time,
+                rest_time_after_lift: layer.after_lift_time,
+                rest_time_after_retract: layer.after_retract_time,
+                light_pwm: layer.light_pwm,
+                data: match kind {
+                    Kind::Ctb => vec![encode_ctb(LayerDecoder::new(&layer.data))],
+                    Kind::Cbddlp => {
+                        let pixels = layer.decode_pixels(width, height);
+                        (0..anti_alias_level)
+                            .map(|level| encode_cbddlp(&pixels, level, anti_alias_level))
+                            .collect()
+                    }
+                },
+            })
+            .collect();
+
+        Self {
+            kind,
+            version: match kind {
+                Kind::Ctb => 4,
+                Kind::Cbddlp => 2,
+            },
+            header: Header {
+                bed_size: [header.x_size, header.y_size, header.z_size],
+                total_height: header.layer_count as f32 * header.layer_thickness,
+                layer_height: header.layer_thickness,
+                exposure_time: header.exposure_time,
+                bottom_exposure_time: header.bottom_exposure_time,
+                light_off_delay: header.turn_off_time,
+                bottom_layers: header.bottom_layers,
+                resolution: [width, height],
+                print_time: header.printing_time,
+                mirrored: header.x_mirror,
+                anti_alias_level,
+                light_pwm: header.light_pwm,
+                bottom_light_pwm: header.bottom_light_pwm,
+                encryption_key: 0,
+            },
+            print_parameters: PrintParameters {
+                bottom_lift_height: header.bottom_lift_distance,
+                bottom_lift_speed: header.bottom_lift_speed,
+                lift_height: header.lift_distance,
+                lift_speed: header.lift_speed,
+                retract_speed: header.retract_speed,
+                volume: header.total_volume,
+                weight: header.total_weight,
+                cost: header.total_price,
+                bottom_light_off_delay: header.turn_off_time,
+                light_off_delay: header.turn_off_time,
+                bottom_layers: header.bottom_layers,
+            },
+            slicer_info: SlicerInfo {
+                bottom_lift_height_2: header.bottom_second_lift_distance,
+                bottom_lift_speed_2: header.bottom_second_lift_speed,
+                lift_height_2: header.second_lift_distance,
+                lift_speed_2: header.second_lift_speed,
+                retract_height_2: header.second_retract_distance,
+                retract_speed_2: header.second_retract_speed,
+                rest_time_after_lift: header.after_lift_time,
+                per_layer_settings: if header.advance_mode {
+                    PER_LAYER_SETTINGS
+                } else {
+                    0
+                },
+                rest_time_after_retract: header.after_retract_time,
+                rest_time_after_lift_2: 0.0,
+                transition_layers: header.transition_layers as u32,
+                anti_alias_level,
+                ..Default::default()
+            },
+            print_parameters_v4: PrintParametersV4 {
+                bottom_retract_speed: header.bottom_retract_speed,
+                bottom_retract_speed_2: header.bottom_second_retract_speed,
+                rest_time_after_retract: header.after_retract_time,
+                rest_time_after_lift: header.after_lift_time,
+                rest_time_before_lift: header.before_lift_time,
+                bottom_retract_height_2: header.bottom_second_retract_distance,
+            },
+            machine_name: header.printer_name.to_string(),
+            large_preview: Preview::from_goo(&header.big_preview),
+            small_preview: Preview::from_goo(&header.small_preview),
+            layers,
+        }
+    }
+
+    /// Converts this file into a `GooFile`.
+    pub fn to_goo(&self) -> Result<GooFile> {
+        let (header, parameters) = (&self.header, &self.print_parameters);
+        let (slicer, parameters_v4) = (&self.slicer_info, &self.print_parameters_v4);
+        let (width, height) = self.resolution();
+
+        let mut layers = Vec::with_capacity(self.layers.len());
+        for (i, layer) in self.layers.iter().enumerate() {
+            let mut content = LayerContent {
+                pause_position_z: header.bed_size[2],
+                layer_position_z: layer.position_z,
+                layer_exposure_time: layer.exposure_time,
+                layer_off_time: layer.light_off_delay,
+                before_lift_time: layer.rest_time_before_lift,
+                after_lift_time: layer.rest_time_after_lift,
+                after_retract_time: layer.rest_time_after_retract,
+                lift_distance: layer.lift_height,
+                lift_speed: layer.lift_speed,
+                second_lift_distance: layer.lift_height_2,
+                second_lift_speed: layer.lift_speed_2,
+                retract_distance: layer.first_retract_height(),
+                retract_speed: layer.retract_speed,
+                second_retract_distance: layer.retract_height_2,
+                second_retract_speed: layer.retract_speed_2,
+                light_pwm: layer.light_pwm,
+                ..Default::default()
+            };
+
+            match self.kind {
+                Kind::Ctb => {
+                    // Pad or cut the runs to exactly fill the layer, like `decode_pixels`
+                    let mut encoder = LayerEncoder::new();
+                    let mut remaining = width as u64 * height as u64;
+                    for Run { length, value } in decode_ctb(&layer.data[0])? {
+                        let length = length.min(remaining);
+                        if length > 0 {
+                            encoder.add_run(length, value);
+                            remaining -= length;
+                        }
+                    }
+                    if remaining > 0 {
+                        encoder.add_run(remaining, 0);
+                    }
+                    (content.data, content.checksum) = encoder.finish();
+                }
+                Kind::Cbddlp => content.set_pixels(width, height, &self.decode_pixels(i)?),
+            }
+
+            layers.push(content);
+        }
+
+        Ok(GooFile::new(
+            HeaderInfo {
+                printer_name: SizedString::new(truncate(self.machine_name.as_bytes(), 32)),
+                anti_aliasing_level: header.anti_alias_level as u16,
+                small_preview: self.small_preview.to_goo(),
+                big_preview: self.large_preview.to_goo(),
+                layer_count: self.layers.len() as u32,
+                x_resolution: width as u16,
+                y_resolution: height as u16,
+                x_mirror: header.mirrored,
+                x_size: header.bed_size[0],
+                y_size: header.bed_size[1],
+                z_size: header.bed_size[2],
+                layer_thickness: header.layer_height,
+                exposure_time: header.exposure_time,
+                turn_off_time: header.light_off_delay,
+                before_lift_time: parameters_v4.rest_time_before_lift,
+                after_lift_time: slicer.rest_time_after_lift,
+                after_retract_time: slicer.rest_time_after_retract,
+                bottom_exposure_time: header.bottom_exposure_time,
+                bottom_layers: header.bottom_layers,
+                bottom_lift_distance: parameters.bottom_lift_height,
+                bottom_lift_speed: parameters.bottom_lift_speed,
+                lift_distance: parameters.lift_height,
+                lift_speed: parameters.lift_speed,
+                bottom_retract_distance: (parameters.bottom_lift_height
+                    + slicer.bottom_lift_height_2
+                    - parameters_v4.bottom_retract_height_2)
+                    .max(0.0),
+                bottom_retract_speed: parameters_v4.bottom_retract_speed,
+                retract_distance: (parameters.lift_height + slicer.lift_height_2
+                    - slicer.retract_height_2)
+                    .max(0.0),
+                retract_speed: parameters.retract_speed,
+                bottom_second_lift_distance: slicer.bottom_lift_height_2,
+                bottom_second_lift_speed: slicer.bottom_lift_speed_2,
+                second_lift_distance: slicer.lift_height_2,
+                second_lift_speed: slicer.lift_speed_2,
+                bottom_second_retract_distance: parameters_v4.bottom_retract_height_2,
+                bottom_second_retract_speed: parameters_v4.bottom_retract_speed_2,
+                second_retract_distance: slicer.retract_height_2,
+                second_retract_speed: slicer.retract_speed_2,
+                bottom_light_pwm: header.bottom_light_pwm,
+                light_pwm: header.light_pwm,
+                advance_mode: slicer.per_layer_settings != 0,
+                printing_time: header.print_time,
+                total_volume: parameters.volume,
+                total_weight: parameters.weight,
+                total_price: parameters.cost,
+                transition_layers: slicer.transition_layers as u16,
+                ..Default::default()
+            },
+            layers,
+        ))
+    }
+}
+
+impl SlicedFile for File {
+    fn info(&self) -> FileInfo {
+        let [x, y, z] = self.header.bed_size;
+        FileInfo {
+            resolution: self.resolution(),
+            size: (x, y, z),
+            layer_thickness: self.header.layer_height,
+            bottom_layers: self.header.bottom_layers,
+            exposure_time: self.header.exposure_time,
+            bottom_exposure_time: self.header.bottom_exposure_time,
+            print_time: self.header.print_time,
+        }
+    }
+
+    fn layer_count(&self) -> usize {
+        self.layers.len()
+    }
+
+    fn layer_info(&self, layer: usize) -> LayerInfo {
+        let layer = &self.layers[layer];
+        LayerInfo {
+            position_z: layer.position_z,
+            exposure_time: layer.exposure_time,
+            lift_distance: layer.lift_height + layer.lift_height_2,
+            lift_speed: layer.lift_speed,
+            retract_distance: layer.first_retract_height() + layer.retract_height_2,
+            retract_speed: layer.retract_speed,
+            light_pwm: layer.light_pwm,
+        }
+    }
+
+    fn decode_layer(&self, layer: usize) -> Result<Vec<u8>> {
+        self.decode_pixels(layer)
+    }
+}
+
+/// Offsets of the sections referenced by the header.
+struct Offsets {
+    large_preview: u32,
+    small_preview: u32,
+    print_parameters: u32,
+    slicer_info: u32,
+    layer_table: u32,
+}
+
+impl Header {
+    fn serialize(&self, ser: &mut Writer, file: &File, offsets: Offsets) {
+        ser.write_u32(match file.kind {
+            Kind::Ctb => MAGIC_CTB,
+            Kind::Cbddlp => MAGIC_CBDDLP,
+        });
+        ser.write_u32(file.version);
+        for size in self.bed_size {
+            ser.write_f32(size);
+        }
+        ser.write_bytes(&[0; 8]);
+        ser.write_f32(self.total_height);
+        ser.write_f32(self.layer_height);
+        ser.write_f32(self.exposure_time);
+        ser.write_f32(self.bottom_exposure_time);
+        ser.write_f32(self.light_off_delay);
+        ser.write_u32(self.bottom_layers);
+        ser.write_u32(self.resolution[0]);
+        ser.write_u32(self.resolution[1]);
+        ser.write_u32(offsets.large_preview);
+        ser.write_u32(offsets.layer_table);
+        ser.write_u32(file.layers.len() as u32);
+        ser.write_u32(offsets.small_preview);
+        ser.write_u32(self.print_time);
+        ser.write_u32(self.mirrored as u32);
+        ser.write_u32(offsets.print_parameters);
+        ser.write_u32(PRINT_PARAMETERS_SIZE);
+        ser.write_u32(self.anti_alias_level);
+        ser.write_u16(self.light_pwm);
+        ser.write_u16(self.bottom_light_pwm);
+        ser.write_u32(match file.kind {
+            Kind::Ctb => self.encryption_key,
+            Kind::Cbddlp => 0,
+        });
+        ser.write_u32(offsets.slicer_info);
+        ser.write_u32(SlicerInfo::SIZE);
+    }
+}
+
+impl PrintParameters {
+    fn from_header(header: &Header) -> Self {
+        Self {
+            bottom_layers: header.bottom_layers,
+            light_off_delay: header.light_off_delay,
+            bottom_light_off_delay: header.light_off_delay,
+            ..Default::default()
+        }
+    }
+
+    fn deserialize(des: &mut Reader) -> Result<Self> {
+        let out = Self {
+            bottom_lift_height: des.read_f32()?,
+            bottom_lift_speed: des.read_f32()?,
+            lift_height: des.read_f32()?,
+            lift_speed: des.read_f32()?,
+            retract_speed: des.read_f32()?,
+            volume: des.read_f32()?,
+            weight: des.read_f32()?,
+            cost: des.read_f32()?,
+            bottom_light_off_delay: des.read_f32()?,
+            light_off_delay: des.read_f32()?,
+            bottom_layers: des.read_u32()?,
+        };
+        des.read_bytes(16)?;
+        Ok(out)
+    }
+
+    fn serialize(&self, ser: &mut Writer) {
+        ser.write_f32(self.bottom_lift_height);
+        ser.write_f32(self.bottom_lift_speed);
+        ser.write_f32(self.lift_height);
+        ser.write_f32(self.lift_speed);
+        ser.write_f32(self.retract_speed);
+        ser.write_f32(self.volume);
+        ser.write_f32(self.weight);
+        ser.write_f32(self.cost);
+        ser.write_f32(self.bottom_light_off_delay);
+        ser.write_f32(self.light_off_delay);
+        ser.write_u32(self.bottom_layers);
+        ser.write_bytes(&[0; 16]);
+    }
+}
+
+impl SlicerInfo {
+    const SIZE: u32 = 76;
+
+    /// Returns the slicer info along with the offset and size of the machine name and the offset of the v4 print parameters.
+    fn deserialize(des: &mut Reader) -> Result<(Self, u32, u32, u32)> {
+        let bottom_lift_height_2 = des.read_f32()?;
+        let bottom_lift_speed_2 = des.read_f32()?;
+        let lift_height_2 = des.read_f32()?;
+        let lift_speed_2 = des.read_f32()?;
+        let retract_height_2 = des.read_f32()?;
+        let retract_speed_2 = des.read_f32()?;
+        let rest_time_after_lift = des.read_f32()?;
+        let machine_name_offset = des.read_u32()?;
+        let machine_name_size = des.read_u32()?;
+        let _anti_alias_flag = des.read_u8()?;
+        des.read_u16()?;
+        let per_layer_settings = des.read_u8()?;
+        let timestamp_minutes = des.read_u32()?;
+        let anti_alias_level = des.read_u32()?;
+        let software_version = des.read_u32()?;
+        let rest_time_after_retract = des.read_f32()?;
+        let rest_time_after_lift_2 = des.read_f32()?;
+        let transition_layers = des.read_u32()?;
+        let print_parameters_v4_offset = des.read_u32()?;
+        des.read_bytes(8)?;
+
+        let out = Self {
+            bottom_lift_height_2,
+            bottom_lift_speed_2,
+            lift_height_2,
+            lift_speed_2,
+            retract_height_2,
+            retract_speed_2,
+            rest_time_after_lift,
+            per_layer_settings,
+            timestamp_minutes,
+            anti_alias_level,
+            software_version,
+            rest_time_after_retract,
+            rest_time_after_lift_2,
+            transition_layers,
+        };
+        Ok((
+            out,
+            machine_name_offset,
+            machine_name_size,
+            print_parameters_v4_offset,
+        ))
+    }
+
+    /// Writes the slicer info, expecting the machine name to directly follow it.
+    /// Returns the position of the v4 print parameters offset so it can be patched later.
+    fn serialize(&self, ser: &mut Writer, machine_name: &[u8], anti_alias_level: u32) -> usize {
+        let start = ser.pos() as u32;
+        ser.write_f32(self.bottom_lift_height_2);
+        ser.write_f32(self.bottom_lift_speed_2);
+        ser.write_f32(self.lift_height_2);
+        ser.write_f32(self.lift_speed_2);
+        ser.write_f32(self.retract_height_2);
+        ser.write_f32(self.retract_speed_2);
+        ser.write_f32(self.rest_time_after_lift);
+        ser.write_u32(start + Self::SIZE);
+        ser.write_u32(machine_name.len() as u32);
+        ser.write_u8(if self.per_layer_settings != 0 {
+            0x0F
+        } else {
+            0x07
+        });
+        ser.write_u16(0);
+        ser.write_u8(self.per_layer_settings);
+        ser.write_u32(self.timestamp_minutes);
+        ser.write_u32(anti_alias_level);
+        ser.write_u32(self.software_version);
+        ser.write_f32(self.rest_time_after_retract);
+        ser.write_f32(self.rest_time_after_lift_2);
+        ser.write_u32(self.transition_layers);
+        let print_parameters_v4 = ser.pos();
+        ser.write_u32(0);
+        ser.write_bytes(&[0; 8]);
+        print_parameters_v4
+    }
+}
+
+impl PrintParametersV4 {
+    fn from_parameters(parameters: &PrintParameters, slicer: &SlicerInfo) -> Self {
+        Self {
+            bottom_retract_speed: parameters.retract_speed,
+            bottom_retract_speed_2: slicer.retract_speed_2,
+            rest_time_after_retract: slicer.rest_time_after_retract,
+            rest_time_after_lift: slicer.rest_time_after_lift,
+            rest_time_before_lift: 0.0,
+            bottom_retract_height_2: slicer.retract_height_2,
+        }
+    }
+
+    fn deserialize(des: &mut Reader) -> Result<Self> {
+        let bottom_retract_speed = des.read_f32()?;
+        let bottom_retract_speed_2 = des.read_f32()?;
+        des.read_bytes(16)?;
+        Ok(Self {
+            bottom_retract_speed,
+            bottom_retract_speed_2,
+            rest_time_after_retract: des.read_f32()?,
+            rest_time_after_lift: des.read_f32()?,
+            rest_time_before_lift: des.read_f32()?,
+            bottom_retract_height_2: des.read_f32()?,
+        })
+    }
+
+    fn serialize(&self, ser: &mut Writer, layer_count: u32) {
+        ser.write_f32(self.bottom_retract_speed);
+        ser.write_f32(self.bottom_retract_speed_2);
+        // Unknown fields, written with the fixed values Chitubox uses
+        ser.write_u32(0);
+        ser.write_f32(4.0);
+        ser.write_u32(0);
+        ser.write_f32(4.0);
+        ser.write_f32(self.rest_time_after_retract);
+        ser.write_f32(self.rest_time_after_lift);
+        ser.write_f32(self.rest_time_before_lift);
+        ser.write_f32(self.bottom_retract_height_2);
+        // Unknown fields, written with the fixed values Chitubox uses
+        ser.write_f32(2955.52);
+        ser.write_u32(73470);
+        ser.write_u32(5);
+        ser.write_u32(layer_count.saturating_sub(1)); // last layer index
+        ser.write_bytes(&[0; 16]);
+        ser.write_u32(0); // disclaimer offset
+        ser.write_u32(0); // disclaimer length
+        ser.write_bytes(&[0; PRINT_PARAMETERS_V4_RESERVED]);
+    }
+}
+
+impl Layer {
+    /// The distance moved at `retract_speed` before the second retract stage.
+    pub fn first_retract_height(&self) -> f32 {
+        (self.lift_height + self.lift_height_2 - self.retract_height_2).max(0.0)
+    }
+
+    /// Creates a layer with the global parameters, used when the file has no per-layer parameters.
+    fn from_globals(
+        header: &Header,
+        parameters: &PrintParameters,
+        slicer: &SlicerInfo,
+        parameters_v4: &PrintParametersV4,
+        layer: u32,
+    ) -> Self {
+        let bottom = layer < header.bottom_layers;
+        let pick = |bottom_value, value| if bottom { bottom_value } else { value };
+
+        Self {
+            position_z: header.layer_height * (layer + 1) as f32,
+            exposure_time: pick(header.bottom_exposure_time, header.exposure_time),
+            light_off_delay: pick(
+                parameters.bottom_light_off_delay,
+                parameters.light_off_delay,
+            ),
+            lift_height: pick(parameters.bottom_lift_height, parameters.lift_height),
+            lift_speed: pick(parameters.bottom_lift_speed, parameters.lift_speed),
+            lift_height_2: pick(slicer.bottom_lift_height_2, slicer.lift_height_2),
+            lift_speed_2: pick(slicer.bottom_lift_speed_2, slicer.lift_speed_2),
+            retract_speed: pick(parameters_v4.bottom_retract_speed, parameters.retract_speed),
+            retract_height_2: pick(
+                parameters_v4.bottom_retract_height_2,
+                slicer.retract_height_2,
+            ),
+            retract_speed_2: pick(parameters_v4.bottom_retract_speed_2, slicer.retract_speed_2),
+            rest_time_before_lift: parameters_v4.rest_time_before_lift,
+            rest_time_after_lift: slicer.rest_time_after_lift,
+            rest_time_after_retract: slicer.rest_time_after_retract,
+            light_pwm: if bottom {
+                header.bottom_light_pwm
+            } else {
+                header.light_pwm
+            },
+            data: Vec::new(),
+        }
+    }
+
+    fn deserialize_extended(&mut self, des: &mut Reader) -> Result<()> {
+        let _size = des.read_u32()?;
+        self.lift_height = des.read_f32()?;
+        self.lift_speed = des.read_f32()?;
+        self.lift_height_2 = des.read_f32()?;
+        self.lift_speed_2 = des.read_f32()?;
+        self.retract_speed = des.read_f32()?;
+        self.retract_height_2 = des.read_f32()?;
+        self.retract_speed_2 = des.read_f32()?;
+        self.rest_time_before_lift = des.read_f32()?;
+        self.rest_time_after_lift = des.read_f32()?;
+        self.rest_time_after_retract = des.read_f32()?;
+        self.light_pwm = des.read_f32()? as u16;
+        Ok(())
+    }
+
+    fn serialize_definition(&self, ser: &mut Writer, data_address: usize, data_size: usize) {
+        ser.write_f32(self.position_z);
+        ser.write_f32(self.exposure_time);
+        ser.write_f32(self.light_off_delay);
+        ser.write_u32(data_address as u32);
+        ser.write_u32(data_size as u32);
+        ser.write_u32((data_address >> 32) as u32);
+        ser.write_u32(LAYER_DEF_SIZE + LAYER_DEF_EX_SIZE);
+        ser.write_bytes(&[0; 8]);
+    }
+
+    fn serialize_extended(&self, ser: &mut Writer) {
+        ser.write_u32(LAYER_DEF_SIZE + LAYER_DEF_EX_SIZE);
+        ser.write_f32(self.lift_height);
+        ser.write_f32(self.lift_speed);
+        ser.write_f32(self.lift_height_2);
+        ser.write_f32(self.lift_speed_2);
+        ser.write_f32(self.retract_speed);
+        ser.write_f32(self.retract_height_2);
+        ser.write_f32(self.retract_speed_2);
+        ser.write_f32(self.rest_time_before_lift);
+        ser.write_f32(self.rest_time_after_lift);
+        ser.write_f32(self.rest_time_after_retract);
+        ser.write_f32(self.light_pwm as f32);
+    }
+}
+
+impl Default for PrintParameters {
+    fn default() -> Self {
+        Self {
+            bottom_lift_height: 5.0,
+            bottom_lift_speed: 65.0,
+            lift_height: 5.0,
+            lift_speed: 65.0,
+            retract_speed: 150.0,
+            volume: 0.0,
+            weight: 0.0,
+            cost: 0.0,
+            bottom_light_off_delay: 0.0,
+            light_off_delay: 0.0,
+            bottom_layers: 0,
+        }
+    }
+}
+
+impl Default for SlicerInfo {
+    fn default() -> Self {
+        Self {
+            bottom_lift_height_2: 0.0,
+            bottom_lift_speed_2: 0.0,
+            lift_height_2: 0.0,
+            lift_speed_2: 0.0,
+            retract_height_2: 0.0,
+            retract_speed_2: 0.0,
+            rest_time_after_lift: 0.0,
+            per_layer_settings: 0,
+            timestamp_minutes: 0,
+            anti_alias_level: 1,
+            software_version: SOFTWARE_VERSION,
+            rest_time_after_retract: 0.0,
+            rest_time_after_lift_2: 0.0,
+            transition_layers: 0,
+        }
+    }
+}
+
+fn read_preview(des: &mut Reader, offset: u32) -> Result<Preview> {
+    if offset == 0 {
+        return Ok(Preview::empty(0, 0));
+    }
+
+    des.seek(offset as usize);
+    let width = des.read_u32()?;
+    let height = des.read_u32()?;
+    let data_offset = des.read_u32()?;
+    let data_size = des.read_u32()?;
+
+    pixel_count(width, height)?;
+
+    des.seek(data_offset as usize);
+    let data = des.read_bytes(data_size as usize)?;
+    let mut out = Preview::empty(width, height);
+
+    let (mut i, mut pixel) = (0, 0);
+    while i + 1 < data.len() {
+        let dot = u16::from_le_bytes([data[i], data[i + 1]]);
+        i += 2;
+
+        let mut repeat = 1;
+        if dot & 0x0020 != 0 {
+            ensure!(i + 1 < data.len(), "Truncated preview image");
+            repeat += u16::from_le_bytes([data[i], data[i + 1]]) as usize & 0x0FFF;
+            i += 2;
+        }
+
+        // 0brrrrrgggggxbbbbb -> 0brrrrrggggggbbbbb
+        let green = (dot >> 6) & 0x1F;
+        let color = (dot & 0xF800) | (green << 6) | ((green >> 4) << 5) | (dot & 0x1F);
+        for _ in 0..repeat {
+            if let Some(x) = out.data.get_mut(pixel) {
+                *x = color;
+            }
+            pixel += 1;
+        }
+    }
+
+    Ok(out)
+}
+
+/// Writes a preview header followed by its run length encoded image, returning the offset of the header.
+fn write_preview(ser: &mut Writer, preview: &Preview) -> u32 {
+    let mut data = Writer::new();
+    let mut pixels = preview.data.iter().map(|&x| x & !0x0020).peekable();
+
+    while let Some(color) = pixels.next() {
+        let mut repeat = 0;
+        while repeat < 0x0FFF && pixels.next_if_eq(&color).is_some() {
+            repeat += 1;
+        }
+
+        if repeat == 0 {
+            data.write_u16(color);
+        } else {
+            data.write_u16(color | 0x0020);
+            data.write_u16(repeat | 0x3000);
+        }
+    }
+
+    let data = data.into_inner();
+    let offset = ser.pos() as u32;
+    ser.write_u32(preview.width);
+    ser.write_u32(preview.height);
+    ser.write_u32(offset + 32);
+    ser.write_u32(data.len() as u32);
+    ser.write_bytes(&[0; 16]);
+    ser.write_bytes(&data);
+    offset
+}
+
+/// Decodes the 7-bit grayscale runs used by `.ctb` files.
+fn decode_ctb(data: &[u8]) -> Result<Vec<Run>> {
+    let mut out = Vec::new();
+    let mut i = 0;
+
+    let mut next = || {
+        let byte = data.get(i).copied();
+        i += 1;
+        byte
+    };
+
+    while let Some(head) = next() {
+        let code = head & 0x7F;
+        let mut length = 1;
+
+        if head & 0x80 != 0 {
+            let Some(first) = next() else {
+                bail!("Truncated layer data");
+            };
+
+            let (base, bytes) = match first {
+                0x00..=0x7F => (first as u64, 0),
+                0x80..=0xBF => ((first & 0x3F) as u64, 1),
+                0xC0..=0xDF => ((first & 0x1F) as u64, 2),
+                0xE0..=0xEF => ((first & 0x0F) as u64, 3),
+                _ => bail!("Invalid run length in layer data"),
+            };
+
+            length = base;
+            for _ in 0..bytes {
+                let Some(byte) = next() else {
+                    bail!("Truncated layer data");
+                };
+                length = (length << 8) | byte as u64;
+            }
+        }
+
+        out.push(Run {
+            length,
+            value: (code << 1) | (code & 1),
+        });
+    }
+
+    Ok(out)
+}
+
+/// Encodes runs into the 7-bit grayscale format used by `.ctb` files.
+fn encode_ctb(runs: impl Iterator<Item = Run>) -> Vec<u8> {
+    let mut out = Vec::new();
+    let mut runs = runs.filter(|x| x.length > 0).peekable();
+
+    while let Some(Run { mut length, value }) = runs.next() {
+        let code = value >> 1;
+        while let Some(run) = runs.next_if(|x| x.value >> 1 == code) {
+            length += run.length;
+        }
+
+        while length > 0 {
+            let run = length.min(0x0FFF_FFFF);
+            length -= run;
+
+            if run == 1 {
+                out.push(code);
+                continue;
+            }
+
+            out.push(code | 0x80);
+            match run {
+                0x00..=0x7F => out.push(run as u8),
+                0x80..=0x3FFF => out.extend_from_slice(&[(run >> 8) as u8 | 0x80, run as u8]),
+                0x4000..=0x1F_FFFF => {
+                    out.extend_from_slice(&[(run >> 16) as u8 | 0xC0, (run >> 8) as u8, run as u8])
+                }
+                _ => out.extend_from_slice(&[
+                    (run >> 24) as u8 | 0xE0,
+                    (run >> 16) as u8,
+                    (run >> 8) as u8,
+                    run as u8,
+                ]),
+            }
+        }
+    }
+
+    out
+}
+
+/// Decodes a 1-bit `.cbddlp` bitmap, incrementing `counts` for every set pixel.
+fn decode_cbddlp(data: &[u8], counts: &mut [u32]) {
+    let mut pixel = 0;
+    for &byte in data {
+        let length = (byte & 0x7F) as usize;
+        if byte & 0x80 != 0 {
+            let end = (pixel + length).min(counts.len());
+            for count in &mut counts[pixel.min(end)..end] {
+                *count += 1;
+            }
+        }
+        pixel += length;
+    }
+}
+
+/// Encodes the pixels that are set for `level` out of `levels` anti-aliasing levels into a 1-bit `.cbddlp` bitmap.
+fn encode_cbddlp(pixels: &[u8], level: u32, levels: u32) -> Vec<u8> {
+    let mut out = Vec::new();
+    let mut pixels = pixels
+        .iter()
+        .map(|&x| (x as u32 * levels + 127) / 255 > level)
+        .peekable();
+
+    while let Some(set) = pixels.next() {
+        let mut length = 1;
+        while length < 0x7D && pixels.next_if_eq(&set).is_some() {
+            length += 1;
+        }
+        out.push(((set as u8) << 7) | length);
+    }
+
+    out
+}
+
+/// Encrypts or decrypts the data of a layer, as the XOR cipher is its own inverse.
+fn crypt_layer(key: u32, layer: u32, data: &mut [u8]) {
+    let seed = key.wrapping_mul(0x2D83CDAC).wrapping_add(0xD8A83423);
+    let mut key = layer
+        .wrapping_mul(0x1E1530CD)
+        .wrapping_add(0xEC3D47CD)
+        .wrapping_mul(seed);
+
+    for (i, byte) in data.iter_mut().enumerate() {
+        *byte ^= (key >> (8 * (i % 4))) as u8;
+        if i % 4 == 3 {
+            key = key.wrapping_add(seed);
+        }
+    }
+}
+
+fn truncate(bytes: &[u8], len: usize) -> &[u8] {
+    &bytes[..bytes.len().min(len)]
+}
@@ -0,0 +1,95 @@
+//! Little-endian readers and writers.
+//!
+//! Unlike goo files, most other formats are little-endian and are made of sections pointing to each other by offset, so the reader can seek and the writer can patch values after the fact.
+
+use anyhow::{ensure, Result};
+
+pub struct Reader<'a> {
+    buffer: &'a [u8],
+    offset: usize,
+}
+
+pub struct Writer {
+    buffer: Vec<u8>,
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(buffer: &'a [u8]) -> Self {
+        Self { buffer, offset: 0 }
+    }
+
+    pub fn seek(&mut self, offset: usize) {
+        self.offset = offset;
+    }
+
+    pub fn read_bytes(&mut self, length: usize) -> Result<&'a [u8]> {
+        ensure!(
+            length <= self.buffer.len().saturating_sub(self.offset),
+            "Unexpected end of file at offset {:#x}",
+            self.offset
+        );
+
+        let value = &self.buffer[self.offset..self.offset + length];
+        self.offset += length;
+        Ok(value)
+    }
+
+    pub fn read_u8(&mut self) -> Result<u8> {
+        Ok(self.read_bytes(1)?[0])
+    }
+
+    pub fn read_u16(&mut self) -> Result<u16> {
+        Ok(u16::from_le_bytes(self.read_bytes(2)?.try_into()?))
+    }
+
+    pub fn read_u32(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.read_bytes(4)?.try_into()?))
+    }
+
+    pub fn read_f32(&mut self) -> Result<f32> {
+        Ok(f32::from_le_bytes(self.read_bytes(4)?.try_into()?))
+    }
+}
+
+impl Writer {
+    pub fn new() -> Self {
+        Self { buffer: Vec::new() }
+    }
+
+    pub fn pos(&self) -> usize {
+        self.buffer.len()
+    }
+
+    pub fn write_bytes(&mut self, data: &[u8]) {
+        self.buffer.extend_from_slice(data);
+    }
+
+    pub fn write_u8(&mut self, data: u8) {
+        self.buffer.push(data);
+    }
+
+    pub fn write_u16(&mut self, data: u16) {
+        self.write_bytes(&data.to_le_bytes());
+    }
+
+    pub fn write_u32(&mut self, data: u32) {
+        self.write_bytes(&data.to_le_bytes());
+    }
+
+    pub fn write_f32(&mut self, data: f32) {
+        self.write_bytes(&data.to_le_bytes());
+    }
+
+    /// Overwrites bytes that have already been written at `offset`.
+    pub fn patch_bytes(&mut self, offset: usize, data: &[u8]) {
+        self.buffer[offset..offset + data.len()].copy_from_slice(data);
+    }
+
+    pub fn patch_u32(&mut self, offset: usize, data: u32) {
+        self.patch_bytes(offset, &data.to_le_bytes());
+    }
+
+    pub fn into_inner(self) -> Vec<u8> {
+        self.buffer
+    }
+}
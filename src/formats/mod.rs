@@ -0,0 +1,80 @@
+//! Conversions between goo files and the formats used by other printers.
+//!
+//! Each format has its own `File` type that can be read and written, and converted to and from a [`GooFile`](crate::GooFile).
+
+use anyhow::{ensure, Result};
+
+use crate::preview_image::PreviewImage;
+
+pub mod anycubic;
+pub mod ctb;
+mod le;
+#[cfg(feature = "sl1")]
+pub mod sl1;
+
+/// Largest image a file may declare, far beyond any printer, so damaged headers fail instead of allocating huge buffers.
+const MAX_PIXELS: u64 = 1 << 28;
+
+/// Number of pixels in a `width` by `height` image read from a file.
+fn pixel_count(width: u32, height: u32) -> Result<usize> {
+    let pixels = width as u64 * height as u64;
+    ensure!(
+        pixels <= MAX_PIXELS,
+        "Image size {width}x{height} is too large"
+    );
+    Ok(pixels as usize)
+}
+
+/// An RGB565 preview image of any size, as used by most binary formats.
+#[derive(Clone, Debug)]
+pub struct Preview {
+    pub width: u32,
+    pub height: u32,
+    // 0brrrrrggggggbbbbb
+    pub data: Vec<u16>,
+}
+
+impl Preview {
+    pub fn empty(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            data: vec![0; width as usize * height as usize],
+        }
+    }
+
+    /// Copies a goo preview image.
+    pub fn from_goo<const WIDTH: usize, const HEIGHT: usize>(
+        preview: &PreviewImage<WIDTH, HEIGHT>,
+    ) -> Self {
+        Self {
+            width: WIDTH as u32,
+            height: HEIGHT as u32,
+            data: preview.inner_data().to_vec(),
+        }
+    }
+
+    /// Scales this preview to the size of a goo preview image using nearest neighbor sampling.
+    pub fn to_goo<const WIDTH: usize, const HEIGHT: usize>(&self) -> PreviewImage<WIDTH, HEIGHT> {
+        let mut out = PreviewImage::empty();
+        out.inner_data_mut()
+            .copy_from_slice(&self.resize(WIDTH as u32, HEIGHT as u32).data);
+        out
+    }
+
+    /// Scales this preview using nearest neighbor sampling.
+    pub fn resize(&self, width: u32, height: u32) -> Self {
+        let mut out = Self::empty(width, height);
+        if self.data.is_empty() {
+            return out;
+        }
+
+        for (i, pixel) in out.data.iter_mut().enumerate() {
+            let x = (i % width as usize) * self.width as usize / width as usize;
+            let y = (i / width as usize) * self.height as usize / height as usize;
+            *pixel = self.data[y * self.width as usize + x];
+        }
+
+        out
+    }
+}
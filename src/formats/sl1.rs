@@ -0,0 +1,429 @@
+//! Prusa SL1 and SL1S print archives.
+//!
+//! An SL1 file is a ZIP archive containing a `config.ini` with the print settings, a `prusaslicer.ini` with the full slicer profile, one PNG per layer and some thumbnails.
+//! Lift movements on these printers are handled by the tilt mechanism, so they are not stored in the file and default values are used when converting to a `GooFile`.
+
+use std::{
+    io::{Cursor, Read, Write},
+    str::FromStr,
+};
+
+use anyhow::{bail, ensure, Context, Result};
+use image::{
+    codecs::png::PngEncoder, imageops::FilterType, ExtendedColorType, GrayImage, ImageEncoder,
+    ImageFormat, RgbaImage,
+};
+use zip::{write::SimpleFileOptions, CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::{
+    file::File as GooFile,
+    header_info::HeaderInfo,
+    ini::{Ini, Section},
+    layer_content::LayerContent,
+    misc::{FileInfo, LayerInfo, SlicedFile},
+    preview_image::PreviewImage,
+    serde::SizedString,
+};
+
+const CONFIG: &str = "config.ini";
+const PRUSASLICER_CONFIG: &str = "prusaslicer.ini";
+const THUMBNAIL_DIR: &str = "thumbnail/";
+const THUMBNAIL_SIZES: [(u32, u32); 2] = [(400, 400), (800, 480)];
+
+/// A Prusa SL1 or SL1S archive.
+pub struct File {
+    pub job_dir: String,
+    pub printer_model: String,
+    pub material_name: String,
+
+    pub layer_height: f32,
+    pub exposure_time: f32,
+    pub first_exposure_time: f32,
+    /// Number of layers the exposure fades from `first_exposure_time` to `exposure_time` over.
+    pub fade_layers: u32,
+    /// Estimated print time in seconds.
+    pub print_time: f32,
+    /// Estimated resin usage in milliliters.
+    pub used_material: f32,
+
+    /// Resolution of the display along its long and short edges.
+    pub display_pixels: [u32; 2],
+    /// Size of the display in millimeters along its long and short edges.
+    pub display_size: [f32; 2],
+    pub display_mirror: [bool; 2],
+    /// If the layer images are rotated to have the long edge of the display vertical.
+    pub portrait: bool,
+    pub max_print_height: f32,
+
+    /// PNG encoded grayscale layer images.
+    pub layers: Vec<Vec<u8>>,
+    pub thumbnails: Vec<RgbaImage>,
+}
+
+impl File {
+    /// The width and height of each layer image.
+    pub fn resolution(&self) -> (u32, u32) {
+        let [long, short] = self.display_pixels;
+        if self.portrait {
+            (short, long)
+        } else {
+            (long, short)
+        }
+    }
+
+    /// The physical width and height of each layer image in millimeters.
+    pub fn size(&self) -> (f32, f32) {
+        let [long, short] = self.display_size;
+        if self.portrait {
+            (short, long)
+        } else {
+            (long, short)
+        }
+    }
+
+    /// Decodes the image of a layer.
+    pub fn layer_image(&self, layer: usize) -> Result<GrayImage> {
+        let image = image::load_from_memory_with_format(&self.layers[layer], ImageFormat::Png)?;
+        let image = image.into_luma8();
+        ensure!(
+            image.dimensions() == self.resolution(),
+            "Layer {layer} does not match the display resolution"
+        );
+        Ok(image)
+    }
+}
+
+impl File {
+    pub fn deserialize(buf: &[u8]) -> Result<Self> {
+        let mut archive = ZipArchive::new(Cursor::new(buf))?;
+
+        let config = Ini::parse(&read_string(&mut archive, CONFIG)?)?;
+        let config = config.section("").context("Empty config.ini")?;
+        let slicer = match archive.index_for_name(PRUSASLICER_CONFIG) {
+            Some(_) => Ini::parse(&read_string(&mut archive, PRUSASLICER_CONFIG)?)?,
+            None => Ini::default(),
+        };
+        let slicer = slicer.section("").unwrap_or(config);
+
+        let job_dir = config.get("jobDir").unwrap_or_default().to_owned();
+
+        // Layers are numbered from zero, so count the layer images in the archive
+        let mut names = archive
+            .file_names()
+            .filter_map(|name| {
+                let index = name.strip_prefix(job_dir.as_str())?.strip_suffix(".png")?;
+                let valid = !index.is_empty() && index.bytes().all(|x| x.is_ascii_digit());
+                Some((
+                    index.parse::<usize>().ok().filter(|_| valid)?,
+                    name.to_owned(),
+                ))
+            })
+            .collect::<Vec<_>>();
+        names.sort_unstable();
+        ensure!(
+            names.iter().enumerate().all(|(i, (index, _))| i == *index),
+            "Layer images are not numbered consecutively from zero"
+        );
+
+        let mut layers = Vec::with_capacity(names.len());
+        for (_, name) in names {
+            layers.push(read_bytes(&mut archive, &name)?);
+        }
+
+        let mut thumbnails = Vec::new();
+        let names = archive
+            .file_names()
+            .filter(|x| x.starts_with(THUMBNAIL_DIR) && x.ends_with(".png"))
+            .map(|x| x.to_owned())
+            .collect::<Vec<_>>();
+        for name in names {
+            let image = read_bytes(&mut archive, &name)?;
+            thumbnails
+                .push(image::load_from_memory_with_format(&image, ImageFormat::Png)?.into_rgba8());
+        }
+
+        Ok(Self {
+            job_dir,
+            printer_model: config.get("printerModel").unwrap_or("SL1").to_owned(),
+            material_name: config.get("materialName").unwrap_or_default().to_owned(),
+
+            layer_height: parse(config, "layerHeight")?,
+            exposure_time: parse(config, "expTime")?,
+            first_exposure_time: parse(config, "expTimeFirst")?,
+            fade_layers: parse(config, "numFade")?,
+            print_time: parse(config, "printTime").unwrap_or_default(),
+            used_material: parse(config, "usedMaterial").unwrap_or_default(),
+
+            display_pixels: [
+                parse(slicer, "display_pixels_x")?,
+                parse(slicer, "display_pixels_y")?,
+            ],
+            display_size: [
+                parse(slicer, "display_width")?,
+                parse(slicer, "display_height")?,
+            ],
+            display_mirror: [
+                parse::<u8>(slicer, "display_mirror_x").unwrap_or_default() != 0,
+                parse::<u8>(slicer, "display_mirror_y").unwrap_or_default() != 0,
+            ],
+            portrait: slicer.get("display_orientation") == Some("portrait"),
+            max_print_height: parse(slicer, "max_print_height").unwrap_or(150.0),
+
+            layers,
+            thumbnails,
+        })
+    }
+
+    pub fn serialize(&self) -> Result<Vec<u8>> {
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        let deflated = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+        let stored = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+
+        let mut config = Section::new("");
+        config.set("action", "print");
+        config.set("jobDir", &self.job_dir);
+        config.set("expTime", self.exposure_time);
+        config.set("expTimeFirst", self.first_exposure_time);
+        config.set("layerHeight", self.layer_height);
+        config.set("materialName", &self.material_name);
+        config.set("numFade", self.fade_layers);
+        config.set("printTime", self.print_time);
+        config.set("printerModel", &self.printer_model);
+        config.set("usedMaterial", self.used_material);
+
+        let mut slicer = Section::new("");
+        slicer.set("display_width", self.display_size[0]);
+        slicer.set("display_height", self.display_size[1]);
+        slicer.set("display_pixels_x", self.display_pixels[0]);
+        slicer.set("display_pixels_y", self.display_pixels[1]);
+        slicer.set("display_mirror_x", self.display_mirror[0] as u8);
+        slicer.set("display_mirror_y", self.display_mirror[1] as u8);
+        slicer.set(
+            "display_orientation",
+            if self.portrait {
+                "portrait"
+            } else {
+                "landscape"
+            },
+        );
+        slicer.set("exposure_time", self.exposure_time);
+        slicer.set("initial_exposure_time", self.first_exposure_time);
+        slicer.set("faded_layers", self.fade_layers);
+        slicer.set("layer_height", self.layer_height);
+        slicer.set("max_print_height", self.max_print_height);
+        slicer.set("printer_model", &self.printer_model);
+
+        for (name, section) in [(CONFIG, config), (PRUSASLICER_CONFIG, slicer)] {
+            let mut ini = Ini::default();
+            ini.push(section);
+            zip.start_file(name, deflated)?;
+            zip.write_all(ini.to_string().as_bytes())?;
+        }
+
+        for (i, layer) in self.layers.iter().enumerate() {
+            zip.start_file(format!("{}{i:05}.png", self.job_dir), stored)?;
+            zip.write_all(layer)?;
+        }
+
+        for thumbnail in &self.thumbnails {
+            let (width, height) = thumbnail.dimensions();
+            let mut png = Vec::new();
+            PngEncoder::new(&mut png).write_image(
+                thumbnail.as_raw(),
+                width,
+                height,
+                ExtendedColorType::Rgba8,
+            )?;
+
+            zip.start_file(
+                format!("{THUMBNAIL_DIR}thumbnail{width}x{height}.png"),
+                stored,
+            )?;
+            zip.write_all(&png)?;
+        }
+
+        Ok(zip.finish()?.into_inner())
+    }
+}
+
+impl File {
+    /// Converts a `GooFile` into an SL1 archive.
+    ///
+    /// The display orientation is picked so the long edge of the goo display is `display_pixels[0]`.
+    pub fn from_goo(goo: &GooFile) -> Result<Self> {
+        let header = &goo.header;
+        let (width, height) = (header.x_resolution as u32, header.y_resolution as u32);
+        let portrait = width < height;
+
+        let mut layers = Vec::with_capacity(goo.layers.len());
+        for layer in &goo.layers {
+            let mut png = Vec::new();
+            PngEncoder::new(&mut png).write_image(
+                &layer.decode_pixels(width, height),
+                width,
+                height,
+                ExtendedColorType::L8,
+            )?;
+            layers.push(png);
+        }
+
+        let preview = header.big_preview.to_image();
+        let thumbnails = THUMBNAIL_SIZES
+            .iter()
+            .map(|&(width, height)| {
+                image::imageops::resize(&preview, width, height, FilterType::Triangle)
+            })
+            .collect();
+
+        Ok(Self {
+            job_dir: header.profile_name.to_string(),
+            printer_model: "SL1S".to_owned(),
+            material_name: String::new(),
+
+            layer_height: header.layer_thickness,
+            exposure_time: header.exposure_time,
+            first_exposure_time: header.bottom_exposure_time,
+            fade_layers: header.bottom_layers,
+            print_time: header.printing_time as f32,
+            used_material: header.total_volume,
+
+            display_pixels: [width.max(height), width.min(height)],
+            display_size: if portrait {
+                [header.y_size, header.x_size]
+            } else {
+                [header.x_size, header.y_size]
+            },
+            display_mirror: [header.x_mirror, header.y_mirror],
+            portrait,
+            max_print_height: header.z_size,
+
+            layers,
+            thumbnails,
+        })
+    }
+
+    /// Converts this archive into a `GooFile`.
+    ///
+    /// The first `fade_layers` layers use the first exposure time and all other layers use the normal exposure time.
+    pub fn to_goo(&self) -> Result<GooFile> {
+        let (width, height) = self.resolution();
+        let (x_size, y_size) = self.size();
+
+        let mut header = HeaderInfo {
+            x_resolution: width as u16,
+            y_resolution: height as u16,
+            x_size,
+            y_size,
+            z_size: self.max_print_height,
+            x_mirror: self.display_mirror[0],
+            y_mirror: self.display_mirror[1],
+
+            layer_count: self.layers.len() as u32,
+            layer_thickness: self.layer_height,
+            exposure_time: self.exposure_time,
+            bottom_exposure_time: self.first_exposure_time,
+            bottom_layers: self.fade_layers,
+            printing_time: self.print_time as u32,
+            total_volume: self.used_material,
+            profile_name: SizedString::new(truncate(self.job_dir.as_bytes(), 32)),
+            ..Default::default()
+        };
+
+        if let (Some(small), Some(big)) = (
+            self.thumbnails.iter().min_by_key(|x| x.width()),
+            self.thumbnails.iter().max_by_key(|x| x.width()),
+        ) {
+            header.small_preview = PreviewImage::from_image_scaled(small, FilterType::Triangle);
+            header.big_preview = PreviewImage::from_image_scaled(big, FilterType::Triangle);
+        }
+
+        let mut layers = Vec::with_capacity(self.layers.len());
+        for i in 0..self.layers.len() {
+            let exposure_time = if (i as u32) < self.fade_layers {
+                self.first_exposure_time
+            } else {
+                self.exposure_time
+            };
+
+            let mut layer = LayerContent {
+                layer_position_z: self.layer_height * (i + 1) as f32,
+                layer_exposure_time: exposure_time,
+                pause_position_z: self.max_print_height,
+                ..Default::default()
+            };
+            layer.set_from_image(&self.layer_image(i)?);
+            layers.push(layer);
+        }
+
+        Ok(GooFile::new(header, layers))
+    }
+}
+
+impl SlicedFile for File {
+    fn info(&self) -> FileInfo {
+        let (x, y) = self.size();
+        FileInfo {
+            resolution: self.resolution(),
+            size: (x, y, self.max_print_height),
+            layer_thickness: self.layer_height,
+            bottom_layers: self.fade_layers,
+            exposure_time: self.exposure_time,
+            bottom_exposure_time: self.first_exposure_time,
+            print_time: self.print_time as u32,
+        }
+    }
+
+    fn layer_count(&self) -> usize {
+        self.layers.len()
+    }
+
+    /// SL1 files only store exposure times, so the lift parameters are the goo defaults.
+    fn layer_info(&self, layer: usize) -> LayerInfo {
+        let defaults = LayerContent::default();
+        LayerInfo {
+            position_z: self.layer_height * (layer + 1) as f32,
+            exposure_time: if (layer as u32) < self.fade_layers {
+                self.first_exposure_time
+            } else {
+                self.exposure_time
+            },
+            lift_distance: defaults.lift_distance,
+            lift_speed: defaults.lift_speed,
+            retract_distance: defaults.retract_distance,
+            retract_speed: defaults.retract_speed,
+            light_pwm: defaults.light_pwm,
+        }
+    }
+
+    fn decode_layer(&self, layer: usize) -> Result<Vec<u8>> {
+        Ok(self.layer_image(layer)?.into_raw())
+    }
+}
+
+fn read_bytes(archive: &mut ZipArchive<Cursor<&[u8]>>, name: &str) -> Result<Vec<u8>> {
+    let mut file = archive
+        .by_name(name)
+        .with_context(|| format!("Missing `{name}` in archive"))?;
+    let mut out = Vec::with_capacity(file.size() as usize);
+    file.read_to_end(&mut out)?;
+    Ok(out)
+}
+
+fn read_string(archive: &mut ZipArchive<Cursor<&[u8]>>, name: &str) -> Result<String> {
+    Ok(String::from_utf8(read_bytes(archive, name)?)?)
+}
+
+fn parse<T: FromStr>(section: &Section, key: &str) -> Result<T> {
+    let Some(value) = section.get(key) else {
+        bail!("Missing `{key}` in config");
+    };
+
+    match value.parse() {
+        Ok(value) => Ok(value),
+        Err(_) => bail!("Invalid value for `{key}`: `{value}`"),
+    }
+}
+
+fn truncate(bytes: &[u8], len: usize) -> &[u8] {
+    &bytes[..bytes.len().min(len)]
+}
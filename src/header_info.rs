@@ -11,6 +11,7 @@ use crate::{
 /// The header of a `.goo` file.
 ///
 /// Check the [official format spec](https://github.com/elegooofficial/GOO) for more information.
+#[derive(Clone)]
 pub struct HeaderInfo {
     pub version: SizedString<4>,
     pub software_info: SizedString<32>,
@@ -144,68 +145,68 @@ impl HeaderInfo {
     }
 
     pub fn deserialize(des: &mut Deserializer) -> Result<Self> {
-        let version = des.read_sized_string();
-        ensure!(des.read_bytes(8) == [0x07, 0x00, 0x00, 0x00, 0x44, 0x4C, 0x50, 0x00]);
-        let software_info = des.read_sized_string();
-        let software_version = des.read_sized_string();
-        let file_time = des.read_sized_string();
-        let printer_name = des.read_sized_string();
-        let printer_type = des.read_sized_string();
-        let profile_name = des.read_sized_string();
-        let anti_aliasing_level = des.read_u16();
-        let grey_level = des.read_u16();
-        let blur_level = des.read_u16();
-        let small_preview = PreviewImage::deserializes(des);
-        ensure!(des.read_bytes(2) == [0xd, 0xa]);
-        let big_preview = PreviewImage::deserializes(des);
-        ensure!(des.read_bytes(2) == [0xd, 0xa]);
-        let layer_count = des.read_u32();
-        let x_resolution = des.read_u16();
-        let y_resolution = des.read_u16();
-        let x_mirror = des.read_bool();
-        let y_mirror = des.read_bool();
-        let x_size = des.read_f32();
-        let y_size = des.read_f32();
-        let z_size = des.read_f32();
-        let layer_thickness = des.read_f32();
-        let exposure_time = des.read_f32();
-        let exposure_delay_mode = des.read_bool();
-        let turn_off_time = des.read_f32();
-        let bottom_before_lift_time = des.read_f32();
-        let bottom_after_lift_time = des.read_f32();
-        let bottom_after_retract_time = des.read_f32();
-        let before_lift_time = des.read_f32();
-        let after_lift_time = des.read_f32();
-        let after_retract_time = des.read_f32();
-        let bottom_exposure_time = des.read_f32();
-        let bottom_layers = des.read_u32();
-        let bottom_lift_distance = des.read_f32();
-        let bottom_lift_speed = des.read_f32();
-        let lift_distance = des.read_f32();
-        let lift_speed = des.read_f32();
-        let bottom_retract_distance = des.read_f32();
-        let bottom_retract_speed = des.read_f32();
-        let retract_distance = des.read_f32();
-        let retract_speed = des.read_f32();
-        let bottom_second_lift_distance = des.read_f32();
-        let bottom_second_lift_speed = des.read_f32();
-        let second_lift_distance = des.read_f32();
-        let second_lift_speed = des.read_f32();
-        let bottom_second_retract_distance = des.read_f32();
-        let bottom_second_retract_speed = des.read_f32();
-        let second_retract_distance = des.read_f32();
-        let second_retract_speed = des.read_f32();
-        let bottom_light_pwm = des.read_u16();
-        let light_pwm = des.read_u16();
-        let advance_mode = des.read_bool();
-        let printing_time = des.read_u32();
-        let total_volume = des.read_f32();
-        let total_weight = des.read_f32();
-        let total_price = des.read_f32();
-        let price_unit = des.read_sized_string();
-        ensure!(des.read_u32() == Self::SIZE as u32);
-        let grey_scale_level = des.read_bool();
-        let transition_layers = des.read_u16();
+        let version = des.read_sized_string()?;
+        ensure!(des.read_bytes(8)? == [0x07, 0x00, 0x00, 0x00, 0x44, 0x4C, 0x50, 0x00]);
+        let software_info = des.read_sized_string()?;
+        let software_version = des.read_sized_string()?;
+        let file_time = des.read_sized_string()?;
+        let printer_name = des.read_sized_string()?;
+        let printer_type = des.read_sized_string()?;
+        let profile_name = des.read_sized_string()?;
+        let anti_aliasing_level = des.read_u16()?;
+        let grey_level = des.read_u16()?;
+        let blur_level = des.read_u16()?;
+        let small_preview = PreviewImage::deserializes(des)?;
+        ensure!(des.read_bytes(2)? == [0xd, 0xa]);
+        let big_preview = PreviewImage::deserializes(des)?;
+        ensure!(des.read_bytes(2)? == [0xd, 0xa]);
+        let layer_count = des.read_u32()?;
+        let x_resolution = des.read_u16()?;
+        let y_resolution = des.read_u16()?;
+        let x_mirror = des.read_bool()?;
+        let y_mirror = des.read_bool()?;
+        let x_size = des.read_f32()?;
+        let y_size = des.read_f32()?;
+        let z_size = des.read_f32()?;
+        let layer_thickness = des.read_f32()?;
+        let exposure_time = des.read_f32()?;
+        let exposure_delay_mode = des.read_bool()?;
+        let turn_off_time = des.read_f32()?;
+        let bottom_before_lift_time = des.read_f32()?;
+        let bottom_after_lift_time = des.read_f32()?;
+        let bottom_after_retract_time = des.read_f32()?;
+        let before_lift_time = des.read_f32()?;
+        let after_lift_time = des.read_f32()?;
+        let after_retract_time = des.read_f32()?;
+        let bottom_exposure_time = des.read_f32()?;
+        let bottom_layers = des.read_u32()?;
+        let bottom_lift_distance = des.read_f32()?;
+        let bottom_lift_speed = des.read_f32()?;
+        let lift_distance = des.read_f32()?;
+        let lift_speed = des.read_f32()?;
+        let bottom_retract_distance = des.read_f32()?;
+        let bottom_retract_speed = des.read_f32()?;
+        let retract_distance = des.read_f32()?;
+        let retract_speed = des.read_f32()?;
+        let bottom_second_lift_distance = des.read_f32()?;
+        let bottom_second_lift_speed = des.read_f32()?;
+        let second_lift_distance = des.read_f32()?;
+        let second_lift_speed = des.read_f32()?;
+        let bottom_second_retract_distance = des.read_f32()?;
+        let bottom_second_retract_speed = des.read_f32()?;
+        let second_retract_distance = des.read_f32()?;
+        let second_retract_speed = des.read_f32()?;
+        let bottom_light_pwm = des.read_u16()?;
+        let light_pwm = des.read_u16()?;
+        let advance_mode = des.read_bool()?;
+        let printing_time = des.read_u32()?;
+        let total_volume = des.read_f32()?;
+        let total_weight = des.read_f32()?;
+        let total_price = des.read_f32()?;
+        let price_unit = des.read_sized_string()?;
+        ensure!(des.read_u32()? == Self::SIZE as u32);
+        let grey_scale_level = des.read_bool()?;
+        let transition_layers = des.read_u16()?;
 
         Ok(Self {
             version,
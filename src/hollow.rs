@@ -0,0 +1,299 @@
+//! Hollowing out solid parts to save resin.
+
+use crate::{analysis::Span, file::File};
+
+/// Settings for hollowing a print.
+#[derive(Clone, Debug)]
+pub struct HollowConfig {
+    /// Minimum thickness of the walls left around the hollow in mm.
+    pub wall_thickness: f32,
+    /// Lattice to leave inside the hollow for support.
+    pub infill: Option<Infill>,
+    /// Holes to cut through the bottom of the part so resin can drain out of the hollow.
+    pub drain_holes: Vec<DrainHole>,
+}
+
+/// A lattice of beams along the X, Y and Z axes.
+#[derive(Clone, Debug)]
+pub struct Infill {
+    /// Distance between beams in mm.
+    pub spacing: f32,
+    /// Thickness of each beam in mm.
+    pub thickness: f32,
+}
+
+/// A round hole cut up through the bottom wall of the part into the hollow.
+///
+/// Holes that don't reach the hollow within the bottom wall thickness are left uncut.
+#[derive(Clone, Debug)]
+pub struct DrainHole {
+    /// Center of the hole on the build plate in mm.
+    pub position: (f32, f32),
+    pub diameter: f32,
+}
+
+impl File {
+    /// Hollows out the print, leaving walls at least `wall_thickness` thick, then cuts the drain holes.
+    ///
+    /// A pixel is removed if every pixel within the wall thickness on its layer, and on each layer within the wall thickness above and below it, is exposed.
+    /// Space above and below the layer stack counts as unexposed, so the top and bottom of the print get walls too.
+    pub fn hollow(&mut self, config: &HollowConfig) {
+        let Some(bounds) = self.bounds() else {
+            return;
+        };
+
+        let (width, height) = self.resolution();
+        let (pixel_x, pixel_y) = self.pixel_size();
+        let depth = (config.wall_thickness / self.header.layer_thickness).round() as usize;
+
+        // Find the pixels far enough from the edge of each layer
+        let interior = self
+            .layers
+            .iter()
+            .map(|layer| {
+                let pixels = layer.decode_pixels(width, height);
+                let mut distances = Vec::new();
+                for y in bounds.min.1..bounds.max.1 {
+                    let row = (y * width) as usize;
+                    let row = &pixels[row + bounds.min.0 as usize..row + bounds.max.0 as usize];
+                    distances.extend(row.iter().map(|&x| if x > 0 { f32::INFINITY } else { 0.0 }));
+                }
+
+                let (local_width, local_height) = (bounds.width(), bounds.height());
+                distance_transform(
+                    &mut distances,
+                    local_width,
+                    local_height,
+                    (pixel_x, pixel_y),
+                );
+
+                let wall = config.wall_thickness * config.wall_thickness;
+                let mut spans = Vec::new();
+                for (i, row) in distances.chunks(local_width as usize).enumerate() {
+                    let mut x = 0;
+                    while x < row.len() {
+                        let start = x;
+                        while x < row.len() && row[x] > wall {
+                            x += 1;
+                        }
+                        if x > start {
+                            spans.push(Span {
+                                y: bounds.min.1 + i as u32,
+                                start: bounds.min.0 + start as u32,
+                                end: bounds.min.0 + x as u32,
+                            });
+                        }
+                        x += 1;
+                    }
+                }
+
+                spans
+            })
+            .collect::<Vec<_>>();
+
+        // Number of layers to cut for each drain hole, zero if it would not open into the hollow
+        let drains = config
+            .drain_holes
+            .iter()
+            .map(|hole| self.drain_depth(hole, &interior, depth))
+            .collect::<Vec<_>>();
+
+        for (z, layer) in self.layers.iter_mut().enumerate() {
+            let mut pixels = layer.decode_pixels(width, height);
+
+            // Only keep pixels that are also interior on the surrounding layers
+            if z >= depth && z + depth < interior.len() {
+                let hollow = interior[z - depth..=z + depth]
+                    .iter()
+                    .skip(1)
+                    .fold(interior[z - depth].clone(), |a, b| intersect(&a, b));
+
+                // Use the absolute Z so the lattice lines up with parts of the same print
+                let position_z = layer.layer_position_z - self.header.layer_thickness;
+                for span in hollow {
+                    for x in span.start..span.end {
+                        let position = (x as f32 * pixel_x, span.y as f32 * pixel_y, position_z);
+                        if !config.infill.as_ref().is_some_and(|x| x.contains(position)) {
+                            pixels[(span.y * width + x) as usize] = 0;
+                        }
+                    }
+                }
+            }
+
+            // Cut the drain holes through the bottom wall
+            for (hole, &layers) in config.drain_holes.iter().zip(&drains) {
+                if z >= layers {
+                    continue;
+                }
+
+                let radius = hole.diameter / 2.0;
+                let min = (
+                    ((hole.position.0 - radius) / pixel_x).floor().max(0.0) as u32,
+                    ((hole.position.1 - radius) / pixel_y).floor().max(0.0) as u32,
+                );
+                let max = (
+                    (((hole.position.0 + radius) / pixel_x).ceil() as u32).min(width),
+                    (((hole.position.1 + radius) / pixel_y).ceil() as u32).min(height),
+                );
+
+                for y in min.1..max.1 {
+                    for x in min.0..max.0 {
+                        let dx = (x as f32 + 0.5) * pixel_x - hole.position.0;
+                        let dy = (y as f32 + 0.5) * pixel_y - hole.position.1;
+                        if dx * dx + dy * dy <= radius * radius {
+                            pixels[(y * width + x) as usize] = 0;
+                        }
+                    }
+                }
+            }
+
+            layer.set_pixels(width, height, &pixels);
+        }
+
+        self.update_header();
+    }
+
+    /// Finds how many layers a drain hole has to cut through before its center reaches the hollow.
+    ///
+    /// Returns zero if the center isn't under the part, or if it doesn't reach the hollow within the bottom `depth` layers.
+    fn drain_depth(&self, hole: &DrainHole, interior: &[Vec<Span>], depth: usize) -> usize {
+        let (width, height) = self.resolution();
+        let (pixel_x, pixel_y) = self.pixel_size();
+        let (x, y) = (
+            (hole.position.0 / pixel_x) as u32,
+            (hole.position.1 / pixel_y) as u32,
+        );
+        if x >= width || y >= height {
+            return 0;
+        }
+
+        let inside = |spans: &Vec<Span>| {
+            spans
+                .iter()
+                .any(|span| span.y == y && (span.start..span.end).contains(&x))
+        };
+
+        for z in 0..=depth.min(self.layers.len().saturating_sub(1)) {
+            let hollow = z >= depth
+                && z + depth < interior.len()
+                && interior[z - depth..=z + depth].iter().all(inside);
+            if hollow {
+                return z;
+            }
+
+            let pixels = self.layers[z].decode_pixels(width, height);
+            if pixels[(y * width + x) as usize] == 0 {
+                return 0;
+            }
+        }
+
+        0
+    }
+}
+
+impl Infill {
+    /// If the lattice covers the point `(x, y, z)` in mm.
+    fn contains(&self, (x, y, z): (f32, f32, f32)) -> bool {
+        // Beams along one axis are where the other two coordinates line up with the grid
+        let on_grid = |x: f32| x.rem_euclid(self.spacing) < self.thickness;
+        [x, y, z].into_iter().filter(|&x| on_grid(x)).count() >= 2
+    }
+}
+
+/// Intersects two lists of spans sorted by row and then column.
+fn intersect(a: &[Span], b: &[Span]) -> Vec<Span> {
+    let mut out = Vec::new();
+    let (mut i, mut j) = (0, 0);
+
+    while i < a.len() && j < b.len() {
+        let (x, y) = (&a[i], &b[j]);
+        if x.y == y.y && x.overlaps(y) {
+            out.push(Span {
+                y: x.y,
+                start: x.start.max(y.start),
+                end: x.end.min(y.end),
+            });
+        }
+
+        if (x.y, x.end) < (y.y, y.end) {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+
+    out
+}
+
+/// Replaces each value with the squared distance in mm to the nearest zero, treating everything outside the grid as zero.
+/// Uses the method from *Distance Transforms of Sampled Functions* by Felzenszwalb and Huttenlocher.
+fn distance_transform(values: &mut [f32], width: u32, height: u32, (pixel_x, pixel_y): (f32, f32)) {
+    let (width, height) = (width as usize, height as usize);
+
+    // Pad each line with a zero on both ends for the outside of the grid
+    let mut line = Vec::new();
+    let mut out = Vec::new();
+
+    for x in 0..width {
+        line.clear();
+        line.push(0.0);
+        line.extend((0..height).map(|y| values[y * width + x]));
+        line.push(0.0);
+        distance_transform_1d(&line, pixel_y, &mut out);
+        for y in 0..height {
+            values[y * width + x] = out[y + 1];
+        }
+    }
+
+    for y in 0..height {
+        line.clear();
+        line.push(0.0);
+        line.extend_from_slice(&values[y * width..(y + 1) * width]);
+        line.push(0.0);
+        distance_transform_1d(&line, pixel_x, &mut out);
+        values[y * width..(y + 1) * width].copy_from_slice(&out[1..width + 1]);
+    }
+}
+
+fn distance_transform_1d(values: &[f32], spacing: f32, out: &mut Vec<f32>) {
+    // Lower envelope of the parabolas rooted at each finite value
+    let mut roots = Vec::<usize>::new();
+    let mut bounds = Vec::<f32>::new();
+    let intersection = |p: usize, q: usize| {
+        let (p_x, q_x) = (p as f32 * spacing, q as f32 * spacing);
+        ((values[q] + q_x * q_x) - (values[p] + p_x * p_x)) / (2.0 * (q_x - p_x))
+    };
+
+    for q in (0..values.len()).filter(|&q| values[q].is_finite()) {
+        while let Some(&p) = roots.last() {
+            if intersection(p, q) > *bounds.last().unwrap() {
+                break;
+            }
+            roots.pop();
+            bounds.pop();
+        }
+
+        bounds.push(match roots.last() {
+            Some(&p) => intersection(p, q),
+            None => f32::NEG_INFINITY,
+        });
+        roots.push(q);
+    }
+
+    out.clear();
+    let mut k = 0;
+    for q in 0..values.len() {
+        if roots.is_empty() {
+            out.push(f32::INFINITY);
+            continue;
+        }
+
+        let x = q as f32 * spacing;
+        while k + 1 < roots.len() && bounds[k + 1] < x {
+            k += 1;
+        }
+
+        let distance = x - roots[k] as f32 * spacing;
+        out.push(distance * distance + values[roots[k]]);
+    }
+}
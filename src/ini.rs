@@ -0,0 +1,102 @@
+//! A minimal reader and writer for `key = value` ini files.
+
+use std::fmt::{self, Display, Write};
+
+use anyhow::{bail, Result};
+
+/// An ini document.
+/// Entries before the first `[section]` header are stored in a section with an empty name.
+#[derive(Default)]
+pub struct Ini {
+    pub sections: Vec<Section>,
+}
+
+#[derive(Default)]
+pub struct Section {
+    pub name: String,
+    pub entries: Vec<(String, String)>,
+}
+
+impl Ini {
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut out = Self::default();
+        let mut section = Section::default();
+
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+                continue;
+            }
+
+            if let Some(name) = line.strip_prefix('[').and_then(|x| x.strip_suffix(']')) {
+                out.push(section);
+                section = Section::new(name.trim());
+                continue;
+            }
+
+            let Some((key, value)) = line.split_once('=') else {
+                bail!("Expected `key = value` on line {}", i + 1);
+            };
+            section.set(key.trim(), value.trim());
+        }
+
+        out.push(section);
+        Ok(out)
+    }
+
+    pub fn section(&self, name: &str) -> Option<&Section> {
+        self.sections.iter().find(|x| x.name == name)
+    }
+
+    /// Adds a section, skipping empty unnamed ones.
+    pub fn push(&mut self, section: Section) {
+        if !section.name.is_empty() || !section.entries.is_empty() {
+            self.sections.push(section);
+        }
+    }
+}
+
+impl Section {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            entries: Vec::new(),
+        }
+    }
+
+    #[cfg(feature = "sl1")]
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.entries
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    pub fn set(&mut self, key: impl Into<String>, value: impl ToString) {
+        let (key, value) = (key.into(), value.to_string());
+        match self.entries.iter_mut().find(|(k, _)| *k == key) {
+            Some(entry) => entry.1 = value,
+            None => self.entries.push((key, value)),
+        }
+    }
+}
+
+impl Display for Ini {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, section) in self.sections.iter().enumerate() {
+            if i != 0 {
+                f.write_char('\n')?;
+            }
+
+            if !section.name.is_empty() {
+                writeln!(f, "[{}]", section.name)?;
+            }
+
+            for (key, value) in &section.entries {
+                writeln!(f, "{key} = {value}")?;
+            }
+        }
+
+        Ok(())
+    }
+}
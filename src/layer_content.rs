@@ -1,8 +1,8 @@
-use anyhow::{ensure, Result};
+use anyhow::{bail, ensure, Result};
 
 use crate::{
     encoded_layer::{LayerDecoder, LayerEncoder},
-    misc::Run,
+    misc::{Bounds, Run},
     serde::{Deserializer, Serializer},
     DELIMITER,
 };
@@ -13,6 +13,7 @@ use image::GrayImage;
 /// The header of each layer in a `.goo` file.
 ///
 /// Check the [official format spec](https://github.com/elegooofficial/GOO) for more information.
+#[derive(Clone)]
 pub struct LayerContent {
     pub pause_flag: u16,
     pub pause_position_z: f32,
@@ -63,29 +64,31 @@ impl LayerContent {
     }
 
     pub fn deserialize(des: &mut Deserializer) -> Result<Self> {
-        let pause_flag = des.read_u16();
-        let pause_position_z = des.read_f32();
-        let layer_position_z = des.read_f32();
-        let layer_exposure_time = des.read_f32();
-        let layer_off_time = des.read_f32();
-        let before_lift_time = des.read_f32();
-        let after_lift_time = des.read_f32();
-        let after_retract_time = des.read_f32();
-        let lift_distance = des.read_f32();
-        let lift_speed = des.read_f32();
-        let second_lift_distance = des.read_f32();
-        let second_lift_speed = des.read_f32();
-        let retract_distance = des.read_f32();
-        let retract_speed = des.read_f32();
-        let second_retract_distance = des.read_f32();
-        let second_retract_speed = des.read_f32();
-        let light_pwm = des.read_u16();
-        ensure!(des.read_bytes(2) == DELIMITER);
-        let data_len = des.read_u32() as usize - 2;
-        ensure!(des.read_u8() == 0x55);
-        let data = des.read_bytes(data_len);
-        let checksum = des.read_u8();
-        ensure!(des.read_bytes(2) == DELIMITER);
+        let pause_flag = des.read_u16()?;
+        let pause_position_z = des.read_f32()?;
+        let layer_position_z = des.read_f32()?;
+        let layer_exposure_time = des.read_f32()?;
+        let layer_off_time = des.read_f32()?;
+        let before_lift_time = des.read_f32()?;
+        let after_lift_time = des.read_f32()?;
+        let after_retract_time = des.read_f32()?;
+        let lift_distance = des.read_f32()?;
+        let lift_speed = des.read_f32()?;
+        let second_lift_distance = des.read_f32()?;
+        let second_lift_speed = des.read_f32()?;
+        let retract_distance = des.read_f32()?;
+        let retract_speed = des.read_f32()?;
+        let second_retract_distance = des.read_f32()?;
+        let second_retract_speed = des.read_f32()?;
+        let light_pwm = des.read_u16()?;
+        ensure!(des.read_bytes(2)? == DELIMITER);
+        let Some(data_len) = (des.read_u32()? as usize).checked_sub(2) else {
+            bail!("Layer data is too short");
+        };
+        ensure!(des.read_u8()? == 0x55);
+        let data = des.read_bytes(data_len)?;
+        let checksum = des.read_u8()?;
+        ensure!(des.read_bytes(2)? == DELIMITER);
 
         Ok(Self {
             pause_flag,
@@ -111,6 +114,16 @@ impl LayerContent {
     }
 }
 
+/// What the printer does before printing a layer.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Pause {
+    /// Print the layer normally.
+    None,
+    /// Raise the build plate to `position_z` (in mm) and wait for the print to be resumed.
+    /// Useful for embedding magnets or inserts, or changing resin.
+    Park { position_z: f32 },
+}
+
 pub fn calculate_checksum(data: &[u8]) -> u8 {
     let mut out = 0u8;
     for &byte in data {
@@ -120,6 +133,54 @@ pub fn calculate_checksum(data: &[u8]) -> u8 {
 }
 
 impl LayerContent {
+    pub fn pause(&self) -> Pause {
+        match self.pause_flag {
+            0 => Pause::None,
+            _ => Pause::Park {
+                position_z: self.pause_position_z,
+            },
+        }
+    }
+
+    pub fn set_pause(&mut self, pause: Pause) {
+        match pause {
+            Pause::None => self.pause_flag = 0,
+            Pause::Park { position_z } => {
+                self.pause_flag = 1;
+                self.pause_position_z = position_z;
+            }
+        }
+    }
+
+    /// Estimate the time it takes to print this layer in seconds.
+    ///
+    /// This includes exposure, waits and plate movement, including moving to and from the park position for paused layers, but not the time spent waiting to be resumed.
+    pub fn print_time(&self) -> f32 {
+        // Speeds are in mm/min
+        let travel = |distance: f32, speed: f32| match speed > 0.0 {
+            true => distance / speed * 60.0,
+            false => 0.0,
+        };
+
+        let park = match self.pause() {
+            Pause::None => 0.0,
+            Pause::Park { position_z } => {
+                let distance = (position_z - self.layer_position_z).max(0.0);
+                travel(distance, self.lift_speed) + travel(distance, self.retract_speed)
+            }
+        };
+
+        park + self.layer_exposure_time
+            + self.layer_off_time
+            + self.before_lift_time
+            + travel(self.lift_distance, self.lift_speed)
+            + travel(self.second_lift_distance, self.second_lift_speed)
+            + self.after_lift_time
+            + travel(self.retract_distance, self.retract_speed)
+            + travel(self.second_retract_distance, self.second_retract_speed)
+            + self.after_retract_time
+    }
+
     /// Decode the pixel data of this layer into a flat `Vec<u8>`.
     ///
     /// `width` and `height` should match the resolution of the file.
@@ -133,6 +194,37 @@ impl LayerContent {
         out
     }
 
+    /// Find the smallest rectangle containing every exposed pixel, without decoding the whole layer.
+    ///
+    /// Returns `None` if no pixels are exposed.
+    pub fn bounds(&self, width: u32, height: u32) -> Option<Bounds> {
+        let mut out: Option<Bounds> = None;
+        let mut pixel = 0;
+
+        for Run { length, value } in LayerDecoder::new(&self.data) {
+            let (start, end) = (pixel, pixel + length);
+            pixel = end;
+            if value == 0 || length == 0 {
+                continue;
+            }
+
+            let width = width as u64;
+            let (first_row, last_row) = (start / width, (end - 1) / width);
+            let (min_x, max_x) = match first_row == last_row {
+                true => (start % width, (end - 1) % width + 1),
+                false => (0, width),
+            };
+
+            let run = Bounds {
+                min: (min_x as u32, first_row as u32),
+                max: (max_x as u32, (last_row + 1).min(height as u64) as u32),
+            };
+            out = Some(out.map_or(run, |x| x.union(&run)));
+        }
+
+        out
+    }
+
     /// Replace the pixel data of this layer from a flat slice of pixels.
     ///
     /// The slice length must match `width * height`.
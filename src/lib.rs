@@ -93,21 +93,36 @@
 //! # }
 //! ```
 
+pub mod analysis;
+pub mod contour;
 mod default;
+pub mod diff;
 mod encoded_layer;
+pub mod fields;
 mod file;
+pub mod formats;
 mod header_info;
+pub mod hollow;
+#[cfg(feature = "image")]
+mod ini;
 mod layer_content;
 pub mod misc;
+pub mod motion;
+pub mod plate;
 mod preview_image;
+pub mod rasterize;
 pub mod serde;
 pub mod slice_config;
+pub mod slicer;
+pub mod stats;
+#[cfg(feature = "image")]
+mod unpack;
 
 pub use encoded_layer::{LayerDecoder, LayerEncoder};
 pub use file::File as GooFile;
 pub use header_info::HeaderInfo;
-pub use layer_content::LayerContent;
-pub use misc::Run;
+pub use layer_content::{LayerContent, Pause};
+pub use misc::{Run, SlicedFile};
 pub use preview_image::PreviewImage;
 
 const ENDING_STRING: &[u8] = &[
@@ -1,5 +1,7 @@
 //! Random types.
 
+use anyhow::Result;
+
 use crate::slice_config::SliceConfig;
 
 /// The result of slicing a model.
@@ -15,6 +17,32 @@ pub struct Run {
     pub value: u8,
 }
 
+/// A rectangle of pixels on the build plate.
+/// `min` is inclusive and `max` is exclusive.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Bounds {
+    pub min: (u32, u32),
+    pub max: (u32, u32),
+}
+
+impl Bounds {
+    pub fn width(&self) -> u32 {
+        self.max.0 - self.min.0
+    }
+
+    pub fn height(&self) -> u32 {
+        self.max.1 - self.min.1
+    }
+
+    /// The smallest rectangle containing both rectangles.
+    pub fn union(&self, other: &Self) -> Self {
+        Self {
+            min: (self.min.0.min(other.min.0), self.min.1.min(other.min.1)),
+            max: (self.max.0.max(other.max.0), self.max.1.max(other.max.1)),
+        }
+    }
+}
+
 /// Defines a layer that can be encoded.
 /// This was created to allow for different implementations of encoding layers for deferent formats, but there is only one implementation in this crate.
 pub trait EncodableLayer {
@@ -24,3 +52,47 @@ pub trait EncodableLayer {
     fn add_run(&mut self, length: u64, value: u8);
     fn finish(self, layer: usize, config: &SliceConfig) -> Self::Output;
 }
+
+/// Format-independent information about a sliced file.
+#[derive(Clone, Debug)]
+pub struct FileInfo {
+    /// Width and height of each layer in pixels.
+    pub resolution: (u32, u32),
+    /// Size of the build volume in millimeters.
+    /// Formats that don't store the build height report the height of the print instead.
+    pub size: (f32, f32, f32),
+    pub layer_thickness: f32,
+    pub bottom_layers: u32,
+    pub exposure_time: f32,
+    pub bottom_exposure_time: f32,
+    /// Estimated print time in seconds.
+    pub print_time: u32,
+}
+
+/// Format-independent parameters of a single layer.
+/// Speeds are in mm/min, no matter how the underlying format stores them.
+#[derive(Clone, Debug)]
+pub struct LayerInfo {
+    pub position_z: f32,
+    pub exposure_time: f32,
+    /// Total distance the plate moves up after this layer, over all lift stages.
+    pub lift_distance: f32,
+    /// Speed of the first lift stage.
+    pub lift_speed: f32,
+    /// Total distance the plate moves back down before the next layer, over all retract stages.
+    pub retract_distance: f32,
+    /// Speed of the first retract stage.
+    pub retract_speed: f32,
+    pub light_pwm: u16,
+}
+
+/// A sliced file that can be inspected without knowing its format.
+///
+/// This is the read side counterpart to `EncodableLayer`, it is implemented by `GooFile` and the file types in the `formats` module.
+pub trait SlicedFile {
+    fn info(&self) -> FileInfo;
+    fn layer_count(&self) -> usize;
+    fn layer_info(&self, layer: usize) -> LayerInfo;
+    /// Decodes a layer into a flat `Vec<u8>` of grayscale pixels, row by row.
+    fn decode_layer(&self, layer: usize) -> Result<Vec<u8>>;
+}
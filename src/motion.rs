@@ -0,0 +1,72 @@
+//! Choosing lift settings for each layer from its exposed area.
+
+use crate::file::File;
+
+/// Lift settings for adaptive motion.
+///
+/// Layers between `small_area` and `large_area` use settings interpolated between `small` and `large`.
+#[derive(Clone, Debug)]
+pub struct MotionProfile {
+    /// Layers with at most this much exposed area in mm² use the `small` settings.
+    pub small_area: f32,
+    /// Layers with at least this much exposed area in mm² use the `large` settings.
+    pub large_area: f32,
+    pub small: LiftSettings,
+    pub large: LiftSettings,
+}
+
+/// The two stage lift of a layer.
+/// Distances are in mm and speeds are in mm/min.
+#[derive(Clone, Debug)]
+pub struct LiftSettings {
+    pub lift_distance: f32,
+    pub lift_speed: f32,
+    pub second_lift_distance: f32,
+    pub second_lift_speed: f32,
+}
+
+impl LiftSettings {
+    fn lerp(&self, other: &Self, t: f32) -> Self {
+        let lerp = |a: f32, b: f32| a + (b - a) * t;
+        Self {
+            lift_distance: lerp(self.lift_distance, other.lift_distance),
+            lift_speed: lerp(self.lift_speed, other.lift_speed),
+            second_lift_distance: lerp(self.second_lift_distance, other.second_lift_distance),
+            second_lift_speed: lerp(self.second_lift_speed, other.second_lift_speed),
+        }
+    }
+}
+
+impl File {
+    /// Sets the lift of each layer from its exposed area, so large layers peel off slowly and small layers move quickly.
+    ///
+    /// The retract distances are adjusted so each layer moves back down as far as it lifted. Bottom layers keep their settings.
+    /// This enables `advance_mode` so the printer uses the per-layer settings, and updates the print time.
+    pub fn apply_adaptive_motion(&mut self, profile: &MotionProfile) {
+        let stats = self.stats();
+        let range = (profile.large_area - profile.small_area).max(f32::EPSILON);
+
+        for (layer, stats) in self
+            .layers
+            .iter_mut()
+            .zip(stats)
+            .skip(self.header.bottom_layers as usize)
+        {
+            let t = ((stats.area - profile.small_area) / range).clamp(0.0, 1.0);
+            let lift = profile.small.lerp(&profile.large, t);
+
+            layer.lift_distance = lift.lift_distance;
+            layer.lift_speed = lift.lift_speed;
+            layer.second_lift_distance = lift.second_lift_distance;
+            layer.second_lift_speed = lift.second_lift_speed;
+
+            // Return to the vat by the same distance, keeping the slow final retract stage where it fits
+            let total = lift.lift_distance + lift.second_lift_distance;
+            layer.second_retract_distance = layer.second_retract_distance.min(total);
+            layer.retract_distance = total - layer.second_retract_distance;
+        }
+
+        self.header.advance_mode = true;
+        self.update_header();
+    }
+}
@@ -0,0 +1,375 @@
+//! Operations that move layer content around the build plate.
+
+use anyhow::{bail, ensure, Result};
+
+use crate::{
+    encoded_layer::LayerDecoder,
+    file::File,
+    header_info::HeaderInfo,
+    layer_content::LayerContent,
+    misc::{Bounds, Run},
+};
+
+/// How to pick the layer parameters when merged jobs disagree.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MergePolicy {
+    /// Use the parameters of the first job that has the layer.
+    First,
+    /// Use the parameters of the job with the longest exposure on the layer.
+    LongestExposure,
+    /// Use the parameters of the job with the shortest exposure on the layer.
+    ShortestExposure,
+}
+
+impl File {
+    /// Find the smallest rectangle containing every exposed pixel on any layer.
+    ///
+    /// Returns `None` if no pixels are exposed.
+    pub fn bounds(&self) -> Option<Bounds> {
+        let (width, height) = self.resolution();
+        self.layers
+            .iter()
+            .filter_map(|x| x.bounds(width, height))
+            .reduce(|a, b| a.union(&b))
+    }
+
+    /// Finds every pixel that is exposed on any layer, row by row.
+    pub(crate) fn footprint(&self) -> Vec<bool> {
+        let (width, height) = self.resolution();
+        let mut mask = vec![false; (width * height) as usize];
+
+        for layer in &self.layers {
+            let mut pixel = 0;
+            for Run { length, value } in LayerDecoder::new(&layer.data) {
+                let end = (pixel + length as usize).min(mask.len());
+                if value > 0 {
+                    mask[pixel.min(end)..end].fill(true);
+                }
+                pixel = end;
+            }
+        }
+
+        mask
+    }
+
+    /// Size of a pixel on the build plate in mm.
+    pub fn pixel_size(&self) -> (f32, f32) {
+        let (width, height) = self.resolution();
+        (
+            self.header.x_size / width as f32,
+            self.header.y_size / height as f32,
+        )
+    }
+
+    /// Moves every layer by `(x, y)` pixels.
+    ///
+    /// The previews are redrawn from the moved layers, every other field is kept as is.
+    /// Fails if any exposed pixel would end up off the plate.
+    pub fn translate(&self, offset_px: (i32, i32)) -> Result<Self> {
+        let (width, height) = self.resolution();
+        let mut out = self.clone();
+
+        for (i, layer) in out.layers.iter_mut().enumerate() {
+            let source = layer.decode_pixels(width, height);
+            let mut pixels = vec![0; source.len()];
+            translate_pixels(&source, &mut pixels, (width, height), offset_px)
+                .map_err(|x| x.context(format!("Layer {i}")))?;
+            layer.set_pixels(width, height, &pixels);
+        }
+
+        out.render_previews();
+        Ok(out)
+    }
+
+    /// Moves the model to the center of the build plate, see `translate`.
+    pub fn center(&self) -> Result<Self> {
+        let Some(bounds) = self.bounds() else {
+            bail!("File has no exposed pixels");
+        };
+
+        let (width, height) = self.resolution();
+        let offset = (
+            ((width - bounds.width()) / 2) as i32 - bounds.min.0 as i32,
+            ((height - bounds.height()) / 2) as i32 - bounds.min.1 as i32,
+        );
+        self.translate(offset)
+    }
+
+    /// Packs the separate objects in this file so they are at least `spacing_mm` apart, and centers them on the build plate.
+    ///
+    /// Objects are the connected areas of the combined footprint of every layer. They are placed in rows, tallest first.
+    /// Fails if the objects do not fit on the plate.
+    pub fn arrange(&self, spacing_mm: f32) -> Result<Self> {
+        let (width, height) = self.resolution();
+        let (pixel_x, pixel_y) = self.pixel_size();
+        let gap = (
+            (spacing_mm / pixel_x).round() as u32,
+            (spacing_mm / pixel_y).round() as u32,
+        );
+
+        let (labels, objects) = label_objects(&self.footprint(), width, height);
+        ensure!(!objects.is_empty(), "File has no exposed pixels");
+
+        let mut order = (0..objects.len()).collect::<Vec<_>>();
+        order.sort_by_key(|&i| std::cmp::Reverse(objects[i].height()));
+
+        let mut positions = vec![(0, 0); objects.len()];
+        let (mut x, mut y, mut row_height) = (0, 0, 0);
+        let mut used = (0, 0);
+
+        for i in order {
+            let object = &objects[i];
+            if x > 0 && x + object.width() > width {
+                (x, y) = (0, y + row_height + gap.1);
+                row_height = 0;
+            }
+            ensure!(
+                x + object.width() <= width && y + object.height() <= height,
+                "{} objects don't fit on the build plate",
+                objects.len()
+            );
+
+            positions[i] = (x, y);
+            row_height = row_height.max(object.height());
+            used = (used.0.max(x + object.width()), used.1.max(y + row_height));
+            x += object.width() + gap.0;
+        }
+
+        let origin = ((width - used.0) / 2, (height - used.1) / 2);
+        let offsets = objects
+            .iter()
+            .zip(&positions)
+            .map(|(object, position)| {
+                (
+                    (origin.0 + position.0) as i64 - object.min.0 as i64,
+                    (origin.1 + position.1) as i64 - object.min.1 as i64,
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let mut out = self.clone();
+        for layer in &mut out.layers {
+            let source = layer.decode_pixels(width, height);
+            let mut pixels = vec![0; source.len()];
+
+            for (i, &value) in source.iter().enumerate().filter(|(_, &x)| x > 0) {
+                let (dx, dy) = offsets[labels[i] as usize - 1];
+                let x = (i as u32 % width) as i64 + dx;
+                let y = (i as u32 / width) as i64 + dy;
+                pixels[(y * width as i64 + x) as usize] = value;
+            }
+
+            layer.set_pixels(width, height, &pixels);
+        }
+
+        out.render_previews();
+        Ok(out)
+    }
+
+    /// Replicates the model in a grid of `nx` by `ny` copies, `spacing_mm` apart, centered on the build plate.
+    ///
+    /// Fails if the grid does not fit on the plate.
+    pub fn array(&self, nx: u32, ny: u32, spacing_mm: f32) -> Result<Self> {
+        ensure!(nx > 0 && ny > 0, "Array must have at least one copy");
+        let Some(bounds) = self.bounds() else {
+            bail!("File has no exposed pixels");
+        };
+
+        let (width, height) = self.resolution();
+        let (pixel_x, pixel_y) = self.pixel_size();
+        let gap = (
+            (spacing_mm / pixel_x).round() as u32,
+            (spacing_mm / pixel_y).round() as u32,
+        );
+
+        let step = (bounds.width() + gap.0, bounds.height() + gap.1);
+        let total = (step.0 * nx - gap.0, step.1 * ny - gap.1);
+        ensure!(
+            total.0 <= width && total.1 <= height,
+            "A {nx}x{ny} array needs {:.1}x{:.1} mm but the build plate is only {:.1}x{:.1} mm",
+            total.0 as f32 * pixel_x,
+            total.1 as f32 * pixel_y,
+            self.header.x_size,
+            self.header.y_size
+        );
+
+        let origin = ((width - total.0) / 2, (height - total.1) / 2);
+        let copies = (0..ny)
+            .flat_map(|y| (0..nx).map(move |x| (x, y)))
+            .map(|(x, y)| {
+                let offset = (
+                    (origin.0 + x * step.0) as i32 - bounds.min.0 as i32,
+                    (origin.1 + y * step.1) as i32 - bounds.min.1 as i32,
+                );
+                (self, offset)
+            })
+            .collect::<Vec<_>>();
+
+        Self::merge(&copies, MergePolicy::First)
+    }
+
+    /// Combines several jobs onto one build plate.
+    ///
+    /// Each job is moved by its `(x, y)` offset in pixels, like in `translate`, and the layers are combined by taking the brightest pixel.
+    /// Shorter jobs are padded with empty layers, and the header is taken from the first job.
+    /// The merged file's bottom layers are the leading layers whose parameters `policy` picked from a bottom layer of their job.
+    /// All jobs must have the same resolution and layer thickness.
+    pub fn merge(jobs: &[(&File, (i32, i32))], policy: MergePolicy) -> Result<Self> {
+        let Some(((first, _), rest)) = jobs.split_first() else {
+            bail!("No jobs to merge");
+        };
+
+        let header = &first.header;
+        for (job, _) in rest {
+            ensure!(
+                (job.header.x_resolution, job.header.y_resolution)
+                    == (header.x_resolution, header.y_resolution),
+                "All jobs must have the same resolution"
+            );
+            ensure!(
+                job.header.layer_thickness == header.layer_thickness,
+                "All jobs must have the same layer thickness"
+            );
+        }
+
+        let (width, height) = first.resolution();
+        let layer_count = jobs.iter().map(|(x, _)| x.layers.len()).max().unwrap();
+        let mut layers = Vec::with_capacity(layer_count);
+        let mut bottom_layers = 0;
+
+        for i in 0..layer_count {
+            let present = jobs.iter().filter_map(|(job, offset)| {
+                let bottom = (i as u32) < job.header.bottom_layers;
+                Some((job.layers.get(i)?, bottom, *offset))
+            });
+
+            let mut pixels = vec![0; (width * height) as usize];
+            let mut parameters: Option<(&LayerContent, bool)> = None;
+
+            for (layer, bottom, offset) in present {
+                let source = layer.decode_pixels(width, height);
+                translate_pixels(&source, &mut pixels, (width, height), offset)
+                    .map_err(|x| x.context(format!("Layer {i}")))?;
+
+                parameters = Some(match parameters {
+                    None => (layer, bottom),
+                    Some(best) => match policy {
+                        MergePolicy::First => best,
+                        MergePolicy::LongestExposure
+                            if layer.layer_exposure_time > best.0.layer_exposure_time =>
+                        {
+                            (layer, bottom)
+                        }
+                        MergePolicy::ShortestExposure
+                            if layer.layer_exposure_time < best.0.layer_exposure_time =>
+                        {
+                            (layer, bottom)
+                        }
+                        _ => best,
+                    },
+                });
+            }
+
+            let (parameters, bottom) = parameters.unwrap();
+            if bottom && bottom_layers == i as u32 {
+                bottom_layers += 1;
+            }
+
+            let mut layer = parameters.clone();
+            layer.layer_position_z = header.layer_thickness * (i + 1) as f32;
+            layer.set_pixels(width, height, &pixels);
+            layers.push(layer);
+        }
+
+        let mut out = Self::new(
+            HeaderInfo {
+                bottom_layers,
+                ..header.clone()
+            },
+            layers,
+        );
+        out.update_header();
+        out.render_previews();
+        Ok(out)
+    }
+}
+
+/// Copies the pixels of `source` into `target` moved by `offset`, keeping the brightest value where they overlap.
+/// Fails if any exposed pixel would end up off the plate.
+fn translate_pixels(
+    source: &[u8],
+    target: &mut [u8],
+    (width, height): (u32, u32),
+    (dx, dy): (i32, i32),
+) -> Result<()> {
+    for (i, &value) in source.iter().enumerate().filter(|(_, &x)| x > 0) {
+        let x = (i as u32 % width) as i64 + dx as i64;
+        let y = (i as u32 / width) as i64 + dy as i64;
+        ensure!(
+            (0..width as i64).contains(&x) && (0..height as i64).contains(&y),
+            "Content moved off the build plate"
+        );
+
+        let pixel = &mut target[(y * width as i64 + x) as usize];
+        *pixel = (*pixel).max(value);
+    }
+
+    Ok(())
+}
+
+/// Labels the 8-connected areas of a mask, returning the label of each pixel (starting at one, zero is unlabeled) and the bounds of each area.
+pub(crate) fn label_objects(mask: &[bool], width: u32, height: u32) -> (Vec<u32>, Vec<Bounds>) {
+    let mut labels = vec![0; mask.len()];
+    let mut objects = Vec::new();
+    let mut stack = Vec::new();
+
+    for start in 0..mask.len() {
+        if !mask[start] || labels[start] != 0 {
+            continue;
+        }
+
+        objects.push(Bounds {
+            min: (u32::MAX, u32::MAX),
+            max: (0, 0),
+        });
+        let label = objects.len() as u32;
+        let bounds = objects.last_mut().unwrap();
+
+        labels[start] = label;
+        stack.push(start);
+
+        while let Some(i) = stack.pop() {
+            let (x, y) = (i as u32 % width, i as u32 / width);
+            *bounds = bounds.union(&Bounds {
+                min: (x, y),
+                max: (x + 1, y + 1),
+            });
+
+            for (dx, dy) in NEIGHBORS {
+                let (nx, ny) = (x as i64 + dx, y as i64 + dy);
+                if !(0..width as i64).contains(&nx) || !(0..height as i64).contains(&ny) {
+                    continue;
+                }
+
+                let j = (ny * width as i64 + nx) as usize;
+                if mask[j] && labels[j] == 0 {
+                    labels[j] = label;
+                    stack.push(j);
+                }
+            }
+        }
+    }
+
+    (labels, objects)
+}
+
+const NEIGHBORS: [(i64, i64); 8] = [
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+    (-1, 0),
+    (1, 0),
+    (-1, 1),
+    (0, 1),
+    (1, 1),
+];
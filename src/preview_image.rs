@@ -1,3 +1,4 @@
+use anyhow::Result;
 #[cfg(feature = "image")]
 use image::{imageops::FilterType, RgbaImage};
 
@@ -6,6 +7,7 @@ use crate::serde::{Deserializer, Serializer};
 /// A preview image.
 ///
 /// Note that the red and blue channels are 5 bits, and the green channel is 6 bits.
+#[derive(Clone)]
 pub struct PreviewImage<const WIDTH: usize, const HEIGHT: usize> {
     // 0brrrrrggggggbbbbb
     data: Box<[u16]>,
@@ -49,24 +51,51 @@ impl<const WIDTH: usize, const HEIGHT: usize> PreviewImage<WIDTH, HEIGHT> {
         Self::from_image(&scaled)
     }
 
+    /// Convert this preview into an `image::RgbaImage`.
+    ///
+    /// The low bits of each channel are filled by repeating the high bits, so passing the result back into `from_image` gives the same preview.
+    #[cfg(feature = "image")]
+    pub fn to_image(&self) -> RgbaImage {
+        let mut out = RgbaImage::new(WIDTH as u32, HEIGHT as u32);
+
+        for (pixel, &color) in out.pixels_mut().zip(self.data.iter()) {
+            let red = ((color >> 11) & 0x1F) as u8;
+            let green = ((color >> 5) & 0x3F) as u8;
+            let blue = (color & 0x1F) as u8;
+
+            *pixel = image::Rgba([
+                (red << 3) | (red >> 2),
+                (green << 2) | (green >> 4),
+                (blue << 3) | (blue >> 2),
+                255,
+            ]);
+        }
+
+        out
+    }
+
     pub fn inner_data(&self) -> &[u16] {
         &self.data
     }
 
+    pub fn inner_data_mut(&mut self) -> &mut [u16] {
+        &mut self.data
+    }
+
     pub fn serializes<T: Serializer>(&self, serializer: &mut T) {
         for pixel in self.data.iter() {
             serializer.write_u16(*pixel);
         }
     }
 
-    pub fn deserializes(deserializer: &mut Deserializer) -> Self {
+    pub fn deserializes(deserializer: &mut Deserializer) -> Result<Self> {
         let mut out = Self::empty();
 
         for pixel in out.data.iter_mut() {
-            *pixel = deserializer.read_u16();
+            *pixel = deserializer.read_u16()?;
         }
 
-        out
+        Ok(out)
     }
 
     pub fn set_pixel(&mut self, x: usize, y: usize, color: (f32, f32, f32)) {
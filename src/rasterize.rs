@@ -0,0 +1,182 @@
+//! Turning polygons into layers.
+
+use crate::{contour::Polygon, misc::EncodableLayer};
+
+/// How to decide which areas are inside a set of overlapping polygons.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FillRule {
+    /// Points are inside if they are enclosed by an odd number of polygons.
+    EvenOdd,
+    /// Points are inside if the polygons wind around them a non-zero number of times.
+    NonZero,
+}
+
+/// A scanline rasterizer that writes runs straight into an [`EncodableLayer`].
+///
+/// Only one row of pixels is kept in memory at a time.
+#[derive(Clone, Debug)]
+pub struct Rasterizer {
+    /// Width and height of the layer in pixels.
+    pub resolution: (u32, u32),
+    /// Size of a pixel in the units of the polygons, `(1.0, 1.0)` if the polygons are already in pixels.
+    pub pixel_size: (f32, f32),
+    pub fill_rule: FillRule,
+    /// Number of samples along each axis of a pixel, so each pixel is sampled `samples²` times.
+    /// Anything above one anti-aliases the edges.
+    pub samples: u32,
+}
+
+/// A polygon edge in pixel space, going from `y_min` to `y_max`.
+struct Edge {
+    y_min: f32,
+    y_max: f32,
+    /// X position at `y_min`.
+    x: f32,
+    slope: f32,
+    winding: i32,
+}
+
+impl Rasterizer {
+    /// A rasterizer for polygons in pixel space, using the even-odd rule without anti-aliasing.
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            resolution: (width, height),
+            pixel_size: (1.0, 1.0),
+            fill_rule: FillRule::EvenOdd,
+            samples: 1,
+        }
+    }
+
+    /// Fills `polygons` white on black, adding every pixel of the layer to `out` row by row.
+    pub fn rasterize<Layer: EncodableLayer>(&self, polygons: &[Polygon], out: &mut Layer) {
+        let segments = polygons.iter().flat_map(|polygon| {
+            let points = &polygon.points;
+            (0..points.len()).map(move |i| (points[i], points[(i + 1) % points.len()]))
+        });
+        self.rasterize_segments(segments, out);
+    }
+
+    /// Like [`Rasterizer::rasterize`], but takes the edges of the polygons in any order.
+    ///
+    /// This is useful when the polygons come from slicing a mesh, as the edges don't have to be joined into loops first.
+    /// With the non-zero fill rule the edges of each outline must all go around it in the same direction.
+    pub fn rasterize_segments<Layer: EncodableLayer>(
+        &self,
+        segments: impl IntoIterator<Item = ((f32, f32), (f32, f32))>,
+        out: &mut Layer,
+    ) {
+        let (width, height) = self.resolution;
+        let samples = self.samples.max(1);
+
+        let mut edges = segments
+            .into_iter()
+            .filter_map(|(a, b)| {
+                let a = (a.0 / self.pixel_size.0, a.1 / self.pixel_size.1);
+                let b = (b.0 / self.pixel_size.0, b.1 / self.pixel_size.1);
+                if a.1 == b.1 {
+                    return None;
+                }
+
+                let (top, bottom, winding) = if a.1 < b.1 { (a, b, 1) } else { (b, a, -1) };
+                Some(Edge {
+                    y_min: top.1,
+                    y_max: bottom.1,
+                    x: top.0,
+                    slope: (bottom.0 - top.0) / (bottom.1 - top.1),
+                    winding,
+                })
+            })
+            .collect::<Vec<_>>();
+        edges.sort_by(|a, b| a.y_min.total_cmp(&b.y_min));
+
+        // Coverage of each pixel in samples, and the number of whole pixels covered starting at each pixel
+        let mut coverage = vec![0_u32; width as usize];
+        let mut full = vec![0_i32; width as usize + 1];
+        let mut crossings = Vec::<(f32, i32)>::new();
+        let mut active = Vec::<&Edge>::new();
+        let mut next_edge = 0;
+
+        let mut run = (0_u64, 0_u8);
+        let max_coverage = samples * samples;
+
+        for y in 0..height {
+            coverage.fill(0);
+            full.fill(0);
+
+            for sample_y in 0..samples {
+                let scan = y as f32 + (sample_y as f32 + 0.5) / samples as f32;
+
+                while next_edge < edges.len() && edges[next_edge].y_min <= scan {
+                    active.push(&edges[next_edge]);
+                    next_edge += 1;
+                }
+                active.retain(|x| x.y_max > scan);
+
+                crossings.clear();
+                crossings
+                    .extend((active.iter()).map(|x| (x.x + (scan - x.y_min) * x.slope, x.winding)));
+                crossings.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+                let mut winding = 0;
+                for pair in crossings.windows(2) {
+                    winding += match self.fill_rule {
+                        FillRule::EvenOdd => 1,
+                        FillRule::NonZero => pair[0].1,
+                    };
+                    let inside = match self.fill_rule {
+                        FillRule::EvenOdd => winding % 2 != 0,
+                        FillRule::NonZero => winding != 0,
+                    };
+
+                    if inside {
+                        self.fill_span(pair[0].0, pair[1].0, &mut coverage, &mut full);
+                    }
+                }
+            }
+
+            let mut whole = 0;
+            for (x, pixel) in coverage.iter().enumerate() {
+                whole += full[x];
+                let covered = (*pixel + whole as u32 * samples).min(max_coverage);
+                let value = (covered * 255 / max_coverage) as u8;
+
+                if value == run.1 {
+                    run.0 += 1;
+                } else {
+                    if run.0 > 0 {
+                        out.add_run(run.0, run.1);
+                    }
+                    run = (1, value);
+                }
+            }
+        }
+
+        if run.0 > 0 {
+            out.add_run(run.0, run.1);
+        }
+    }
+
+    /// Adds the samples between `start` and `end` on one sample row to the coverage of the row.
+    fn fill_span(&self, start: f32, end: f32, coverage: &mut [u32], full: &mut [i32]) {
+        let samples = self.samples.max(1) as i64;
+        let columns = coverage.len() as i64 * samples;
+
+        // Samples are at the center of each sample column
+        let first = ((start * samples as f32 - 0.5).ceil() as i64).clamp(0, columns);
+        let last = ((end * samples as f32 - 0.5).ceil() as i64).clamp(0, columns);
+        if first >= last {
+            return;
+        }
+
+        let (first_pixel, last_pixel) = (first / samples, (last - 1) / samples);
+        if first_pixel == last_pixel {
+            coverage[first_pixel as usize] += (last - first) as u32;
+            return;
+        }
+
+        coverage[first_pixel as usize] += ((first_pixel + 1) * samples - first) as u32;
+        coverage[last_pixel as usize] += (last - last_pixel * samples) as u32;
+        full[first_pixel as usize + 1] += 1;
+        full[last_pixel as usize] -= 1;
+    }
+}
@@ -1,5 +1,6 @@
 use std::fmt::{self, Debug, Display};
 
+#[derive(Clone)]
 pub struct SizedString<const SIZE: usize> {
     pub(crate) data: [u8; SIZE],
 }
@@ -24,9 +25,9 @@ impl<const SIZE: usize> SizedString<SIZE> {
     }
 }
 
-impl Display for SizedString<32> {
+impl<const SIZE: usize> Display for SizedString<SIZE> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let null = self.data.iter().position(|&x| x == 0).unwrap_or(32);
+        let null = self.data.iter().position(|&x| x == 0).unwrap_or(SIZE);
         f.write_str(&String::from_utf8_lossy(&self.data[..null]))
     }
 }
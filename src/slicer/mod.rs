@@ -0,0 +1,183 @@
+//! Slicing triangle meshes into layers.
+//!
+//! ```rust,no_run
+//! use goo::{
+//!     slice_config::{ExposureConfig, SliceConfig},
+//!     slicer::{self, Mesh},
+//!     GooFile, LayerEncoder,
+//! };
+//!
+//! # fn test() -> anyhow::Result<()> {
+//! let config = SliceConfig {
+//!     platform_resolution: [11520, 5120],
+//!     platform_size: [218.88, 122.904, 260.0],
+//!     slice_height: 0.05,
+//!     exposure_config: ExposureConfig::default(),
+//!     first_exposure_config: ExposureConfig {
+//!         exposure_time: 30.0,
+//!         ..Default::default()
+//!     },
+//!     first_layers: 3,
+//! };
+//!
+//! let mut mesh = Mesh::load_stl(&std::fs::read("model.stl")?)?;
+//! mesh.place_on_plate(&config);
+//!
+//! let result = slicer::slice::<LayerEncoder>(&mesh, &config);
+//! let goo = GooFile::from_slice_result(result);
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::{
+    misc::{EncodableLayer, SliceResult},
+    rasterize::{FillRule, Rasterizer},
+    slice_config::SliceConfig,
+};
+
+mod obj;
+mod reconstruct;
+mod stl;
+#[cfg(feature = "threemf")]
+mod threemf;
+
+/// A triangle mesh in mm.
+///
+/// Triangles wind counterclockwise when viewed from outside the mesh.
+#[derive(Clone, Debug, Default)]
+pub struct Mesh {
+    pub triangles: Vec<[[f32; 3]; 3]>,
+}
+
+impl Mesh {
+    /// The corners of the smallest box containing the mesh.
+    pub fn bounds(&self) -> ([f32; 3], [f32; 3]) {
+        let mut min = [f32::INFINITY; 3];
+        let mut max = [f32::NEG_INFINITY; 3];
+        for vertex in self.triangles.iter().flatten() {
+            for axis in 0..3 {
+                min[axis] = min[axis].min(vertex[axis]);
+                max[axis] = max[axis].max(vertex[axis]);
+            }
+        }
+        (min, max)
+    }
+
+    pub fn translate(&mut self, offset: [f32; 3]) {
+        for vertex in self.triangles.iter_mut().flatten() {
+            for axis in 0..3 {
+                vertex[axis] += offset[axis];
+            }
+        }
+    }
+
+    /// Multiplies every coordinate by `factor`, for converting between units.
+    pub fn scale(&mut self, factor: f32) {
+        for vertex in self.triangles.iter_mut().flatten() {
+            vertex.iter_mut().for_each(|x| *x *= factor);
+        }
+    }
+
+    /// Combines several meshes into one, so they can be sliced together.
+    pub fn combine(meshes: impl IntoIterator<Item = Mesh>) -> Self {
+        Self {
+            triangles: meshes.into_iter().flat_map(|x| x.triangles).collect(),
+        }
+    }
+
+    /// Centers the mesh on the build plate and moves it down to rest on the plate.
+    pub fn place_on_plate(&mut self, config: &SliceConfig) {
+        let (min, max) = self.bounds();
+        self.translate([
+            config.platform_size[0] / 2.0 - (min[0] + max[0]) / 2.0,
+            config.platform_size[1] / 2.0 - (min[1] + max[1]) / 2.0,
+            -min[2],
+        ]);
+    }
+}
+
+/// Slices a mesh into layers of `config.slice_height`, sampling each layer at its center height.
+///
+/// The X and Y coordinates of the mesh map to the columns and rows of each layer.
+pub fn slice<'a, Layer: EncodableLayer>(
+    mesh: &Mesh,
+    config: &'a SliceConfig,
+) -> SliceResult<'a, Layer::Output> {
+    let (_, max) = mesh.bounds();
+    let layer_count = match max[2] > 0.0 {
+        true => (max[2] / config.slice_height).ceil() as usize,
+        false => 0,
+    };
+
+    let [width, height] = config.platform_resolution;
+    let rasterizer = Rasterizer {
+        pixel_size: (
+            config.platform_size[0] / width as f32,
+            config.platform_size[1] / height as f32,
+        ),
+        fill_rule: FillRule::NonZero,
+        ..Rasterizer::new(width, height)
+    };
+
+    // Triangles sorted by their lowest point, so each layer only looks at the triangles that can cross it
+    let mut triangles = mesh.triangles.clone();
+    triangles.sort_by(|a, b| min_z(a).total_cmp(&min_z(b)));
+
+    let mut active = Vec::new();
+    let mut next = 0;
+    let mut layers = Vec::with_capacity(layer_count);
+
+    for layer in 0..layer_count {
+        let z = (layer as f32 + 0.5) * config.slice_height;
+        while next < triangles.len() && min_z(&triangles[next]) <= z {
+            active.push(triangles[next]);
+            next += 1;
+        }
+        active.retain(|x| max_z(x) > z);
+
+        let mut out = Layer::new();
+        let segments = active.iter().filter_map(|x| intersect(x, z));
+        rasterizer.rasterize_segments(segments, &mut out);
+        layers.push(out.finish(layer, config));
+    }
+
+    SliceResult {
+        layers,
+        slice_config: config,
+    }
+}
+
+fn min_z(triangle: &[[f32; 3]; 3]) -> f32 {
+    triangle[0][2].min(triangle[1][2]).min(triangle[2][2])
+}
+
+fn max_z(triangle: &[[f32; 3]; 3]) -> f32 {
+    triangle[0][2].max(triangle[1][2]).max(triangle[2][2])
+}
+
+/// Finds where a triangle crosses the plane at height `z`.
+///
+/// The segment is directed so that, looking down at the layer, the inside of the mesh is on its left.
+fn intersect(triangle: &[[f32; 3]; 3], z: f32) -> Option<((f32, f32), (f32, f32))> {
+    // Vertices exactly on the plane count as above it, so each crossing edge is found exactly once
+    let above = triangle.map(|x| x[2] >= z);
+    let crossing = |a: usize, b: usize| {
+        let (a, b) = (triangle[a], triangle[b]);
+        let t = (z - a[2]) / (b[2] - a[2]);
+        (a[0] + (b[0] - a[0]) * t, a[1] + (b[1] - a[1]) * t)
+    };
+
+    // Walking around the triangle, find the edge going up through the plane and the edge coming back down
+    let mut up = None;
+    let mut down = None;
+    for i in 0..3 {
+        let j = (i + 1) % 3;
+        match (above[i], above[j]) {
+            (false, true) => up = Some(crossing(i, j)),
+            (true, false) => down = Some(crossing(i, j)),
+            _ => {}
+        }
+    }
+
+    Some((down?, up?))
+}
@@ -0,0 +1,64 @@
+//! Loading Wavefront OBJ files.
+
+use anyhow::{bail, ensure, Context, Result};
+
+use super::Mesh;
+
+impl Mesh {
+    /// Loads the faces of an OBJ file, splitting polygons into triangles.
+    ///
+    /// OBJ files don't store units, so the coordinates are taken to be in mm. Use [`Mesh::scale`] if they aren't.
+    pub fn load_obj(data: &str) -> Result<Self> {
+        let mut vertices = Vec::new();
+        let mut triangles = Vec::new();
+
+        for (line_number, line) in data.lines().enumerate() {
+            let context = || format!("Invalid OBJ file on line {}", line_number + 1);
+            let mut tokens = line.split_whitespace();
+
+            match tokens.next() {
+                Some("v") => {
+                    let mut vertex = [0.0; 3];
+                    for axis in vertex.iter_mut() {
+                        let token = tokens.next().with_context(context)?;
+                        *axis = token.parse().with_context(context)?;
+                    }
+                    vertices.push(vertex);
+                }
+                Some("f") => {
+                    // Each corner is `vertex`, `vertex/texture`, `vertex//normal` or `vertex/texture/normal`
+                    let corners = tokens
+                        .map(|token| {
+                            let index = token.split('/').next().unwrap();
+                            let index = index.parse::<i64>().with_context(context)?;
+                            let index = match index {
+                                1.. => index - 1,
+                                ..0 => vertices.len() as i64 + index,
+                                0 => bail!("{}: vertex indices start at 1", context()),
+                            };
+
+                            ensure!(
+                                (0..vertices.len() as i64).contains(&index),
+                                "{}: vertex index out of bounds",
+                                context()
+                            );
+                            Ok(vertices[index as usize])
+                        })
+                        .collect::<Result<Vec<_>>>()?;
+
+                    ensure!(
+                        corners.len() >= 3,
+                        "{}: face has less than 3 vertices",
+                        context()
+                    );
+                    for i in 1..corners.len() - 1 {
+                        triangles.push([corners[0], corners[i], corners[i + 1]]);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Ok(Self { triangles })
+    }
+}
@@ -0,0 +1,142 @@
+//! Rebuilding meshes from sliced layers.
+
+use crate::{
+    analysis::{Span, Spans},
+    file::File,
+    layer_content::LayerContent,
+};
+
+use super::Mesh;
+
+impl File {
+    /// Rebuilds a mesh from the layers, with each exposed pixel extruded into a box one layer thick.
+    ///
+    /// Boxes are sized by the pixel size and `layer_thickness`, with layer `i` between `i * layer_thickness` and `(i + 1) * layer_thickness`.
+    /// Only the faces between exposed and unexposed pixels are kept, and neighboring pixels in a row share faces, so the mesh is closed.
+    /// Slicing the mesh again with the same resolution and layer thickness gives back the same pixels, ignoring anti-aliasing.
+    pub fn to_mesh(&self) -> Mesh {
+        let (width, height) = self.resolution();
+        let (pixel_x, pixel_y) = self.pixel_size();
+        let thickness = self.header.layer_thickness;
+        // Layers past either end of the stack are empty
+        let empty = LayerContent::default();
+        let spans = |i: Option<usize>| {
+            let layer = i.and_then(|i| self.layers.get(i)).unwrap_or(&empty);
+            Spans::new(layer, width, height)
+        };
+
+        let mut mesh = Mesh::default();
+        let mut below = spans(None);
+        let mut current = spans(Some(0));
+
+        for i in 0..self.layers.len() {
+            let above = spans(Some(i + 1));
+            let (z0, z1) = (i as f32 * thickness, (i + 1) as f32 * thickness);
+
+            for y in 0..height {
+                let row = current.row(y);
+                let (y0, y1) = (y as f32 * pixel_y, (y + 1) as f32 * pixel_y);
+                let x = |x: u32| x as f32 * pixel_x;
+
+                for span in row {
+                    mesh.quad(
+                        [x(span.start), y0, z0],
+                        [0.0, 0.0, thickness],
+                        [0.0, pixel_y, 0.0],
+                    );
+                    mesh.quad(
+                        [x(span.end), y0, z0],
+                        [0.0, pixel_y, 0.0],
+                        [0.0, 0.0, thickness],
+                    );
+                }
+
+                let previous = y.checked_sub(1).map(|y| current.row(y)).unwrap_or_default();
+                for span in subtract(row, previous) {
+                    let length = x(span.end) - x(span.start);
+                    mesh.quad(
+                        [x(span.start), y0, z0],
+                        [length, 0.0, 0.0],
+                        [0.0, 0.0, thickness],
+                    );
+                }
+                for span in subtract(row, current.row(y + 1)) {
+                    let length = x(span.end) - x(span.start);
+                    mesh.quad(
+                        [x(span.start), y1, z0],
+                        [0.0, 0.0, thickness],
+                        [length, 0.0, 0.0],
+                    );
+                }
+
+                for span in subtract(row, below.row(y)) {
+                    let length = x(span.end) - x(span.start);
+                    mesh.quad(
+                        [x(span.start), y0, z0],
+                        [0.0, pixel_y, 0.0],
+                        [length, 0.0, 0.0],
+                    );
+                }
+                for span in subtract(row, above.row(y)) {
+                    let length = x(span.end) - x(span.start);
+                    mesh.quad(
+                        [x(span.start), y0, z1],
+                        [length, 0.0, 0.0],
+                        [0.0, pixel_y, 0.0],
+                    );
+                }
+            }
+
+            below = std::mem::replace(&mut current, above);
+        }
+
+        mesh
+    }
+}
+
+impl Mesh {
+    /// Adds the rectangle with corners `origin`, `origin + u`, `origin + u + v` and `origin + v`, facing along `u × v`.
+    fn quad(&mut self, origin: [f32; 3], u: [f32; 3], v: [f32; 3]) {
+        let corner = |a: f32, b: f32| [0, 1, 2].map(|i| origin[i] + a * u[i] + b * v[i]);
+        let corners = [
+            corner(0.0, 0.0),
+            corner(1.0, 0.0),
+            corner(1.0, 1.0),
+            corner(0.0, 1.0),
+        ];
+        self.triangles.push([corners[0], corners[1], corners[2]]);
+        self.triangles.push([corners[0], corners[2], corners[3]]);
+    }
+}
+
+/// The parts of the spans in `a` that aren't covered by any span in `b`, for two rows sorted by column.
+fn subtract(a: &[Span], b: &[Span]) -> Vec<Span> {
+    let mut out = Vec::new();
+    let mut j = 0;
+
+    for span in a {
+        let mut start = span.start;
+        while j < b.len() && b[j].end <= start {
+            j += 1;
+        }
+
+        let mut k = j;
+        while k < b.len() && b[k].start < span.end {
+            if b[k].start > start {
+                out.push(Span {
+                    end: b[k].start,
+                    start,
+                    ..*span
+                });
+            }
+            start = start.max(b[k].end);
+            k += 1;
+        }
+
+        if start < span.end {
+            out.push(Span { start, ..*span });
+        }
+    }
+
+    out
+}
@@ -0,0 +1,103 @@
+//! Loading binary and ASCII STL files, and saving binary STL files.
+
+use anyhow::{bail, ensure, Context, Result};
+
+use super::Mesh;
+
+impl Mesh {
+    /// Loads a binary or ASCII STL file.
+    ///
+    /// The normals stored in the file are ignored, the winding of each triangle is used instead.
+    pub fn load_stl(data: &[u8]) -> Result<Self> {
+        // ASCII files start with `solid`, but some binary files do too, so check that the size matches first
+        let binary_size = (data.len() >= 84)
+            .then(|| 84 + 50 * u32::from_le_bytes(data[80..84].try_into().unwrap()) as usize);
+
+        match binary_size == Some(data.len()) || !data.starts_with(b"solid") {
+            true => Self::load_binary_stl(data),
+            false => Self::load_ascii_stl(std::str::from_utf8(data)?),
+        }
+    }
+
+    fn load_binary_stl(data: &[u8]) -> Result<Self> {
+        ensure!(data.len() >= 84, "STL file is too short");
+        let count = u32::from_le_bytes(data[80..84].try_into()?) as usize;
+        ensure!(
+            data.len() >= 84 + 50 * count,
+            "STL file has {count} triangles but is only {} bytes",
+            data.len()
+        );
+
+        let float =
+            |offset: usize| f32::from_le_bytes(data[offset..offset + 4].try_into().unwrap());
+        let triangles = (0..count)
+            .map(|i| {
+                // Skip the normal
+                let offset = 84 + 50 * i + 12;
+                [0, 1, 2].map(|vertex| {
+                    let offset = offset + 12 * vertex;
+                    [float(offset), float(offset + 4), float(offset + 8)]
+                })
+            })
+            .collect();
+
+        Ok(Self { triangles })
+    }
+
+    fn load_ascii_stl(data: &str) -> Result<Self> {
+        let mut triangles = Vec::new();
+        let mut vertices = Vec::with_capacity(3);
+        let mut tokens = data.split_whitespace();
+
+        while let Some(token) = tokens.next() {
+            match token {
+                "vertex" => {
+                    let mut vertex = [0.0; 3];
+                    for axis in vertex.iter_mut() {
+                        let token = tokens.next().context("Unexpected end of STL file")?;
+                        *axis = token.parse().context("Invalid vertex in STL file")?;
+                    }
+                    vertices.push(vertex);
+                }
+                "endfacet" => {
+                    let Ok(triangle) = <[[f32; 3]; 3]>::try_from(vertices.as_slice()) else {
+                        bail!("STL facet has {} vertices instead of 3", vertices.len());
+                    };
+                    triangles.push(triangle);
+                    vertices.clear();
+                }
+                _ => {}
+            }
+        }
+
+        Ok(Self { triangles })
+    }
+
+    /// Saves the mesh as a binary STL file, with normals computed from the winding of each triangle.
+    pub fn to_stl(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(84 + 50 * self.triangles.len());
+        out.extend_from_slice(&[0; 80]);
+        out.extend_from_slice(&(self.triangles.len() as u32).to_le_bytes());
+
+        for [a, b, c] in &self.triangles {
+            let (u, v) = (
+                [0, 1, 2].map(|i| b[i] - a[i]),
+                [0, 1, 2].map(|i| c[i] - a[i]),
+            );
+            let normal = [
+                u[1] * v[2] - u[2] * v[1],
+                u[2] * v[0] - u[0] * v[2],
+                u[0] * v[1] - u[1] * v[0],
+            ];
+            let length = normal.iter().map(|x| x * x).sum::<f32>().sqrt();
+            let normal = normal.map(|x| if length > 0.0 { x / length } else { 0.0 });
+
+            for value in [normal, *a, *b, *c].iter().flatten() {
+                out.extend_from_slice(&value.to_le_bytes());
+            }
+            out.extend_from_slice(&[0; 2]);
+        }
+
+        out
+    }
+}
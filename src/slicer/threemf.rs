@@ -0,0 +1,226 @@
+//! Loading 3MF files.
+
+use std::{
+    collections::HashMap,
+    io::{Cursor, Read},
+};
+
+use anyhow::{bail, Context, Result};
+use quick_xml::{
+    events::{BytesStart, Event},
+    Reader,
+};
+use zip::ZipArchive;
+
+use super::Mesh;
+
+const MODEL_RELATIONSHIP: &str = "http://schemas.microsoft.com/3dmanufacturing/2013/01/3dmodel";
+const DEFAULT_MODEL: &str = "3D/3dmodel.model";
+
+/// An affine transform in the 3MF layout, four rows of three columns with the translation in the last row.
+type Transform = [f32; 12];
+const IDENTITY: Transform = [1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0];
+
+#[derive(Default)]
+struct Object {
+    triangles: Vec<[[f32; 3]; 3]>,
+    components: Vec<(String, Transform)>,
+}
+
+impl Mesh {
+    /// Loads every object on the build plate of a 3MF file, returning one mesh per object.
+    ///
+    /// Object and component transforms are applied, and coordinates are converted to mm from the unit of the file.
+    pub fn load_3mf(data: &[u8]) -> Result<Vec<Self>> {
+        let mut archive = ZipArchive::new(Cursor::new(data))?;
+
+        let model_path = match read_file(&mut archive, "_rels/.rels") {
+            Ok(rels) => find_model(&rels)?.unwrap_or_else(|| DEFAULT_MODEL.to_owned()),
+            Err(_) => DEFAULT_MODEL.to_owned(),
+        };
+        let model = read_file(&mut archive, model_path.trim_start_matches('/'))?;
+
+        let mut reader = Reader::from_str(&model);
+        let mut scale = 1.0;
+        let mut objects = HashMap::<String, Object>::new();
+        let mut items = Vec::new();
+        let mut current = None;
+        let mut vertices = Vec::new();
+
+        loop {
+            match reader.read_event()? {
+                Event::Start(element) | Event::Empty(element) => {
+                    match element.local_name().as_ref() {
+                        b"model" => {
+                            if let Some(unit) = attribute(&element, "unit")? {
+                                scale = unit_scale(&unit)?;
+                            }
+                        }
+                        b"object" => {
+                            let id = required(&element, "id")?;
+                            objects.insert(id.clone(), Object::default());
+                            current = Some(id);
+                            vertices.clear();
+                        }
+                        b"vertex" => {
+                            let mut vertex = [0.0; 3];
+                            for (axis, name) in vertex.iter_mut().zip(["x", "y", "z"]) {
+                                *axis = number(&element, name).with_context(|| {
+                                    format!("Vertex {}: invalid `{name}` attribute", vertices.len())
+                                })?;
+                            }
+                            vertices.push(vertex);
+                        }
+                        b"triangle" => {
+                            let object = current_object(&mut objects, &current)?;
+                            let mut triangle = [[0.0; 3]; 3];
+                            for (vertex, name) in triangle.iter_mut().zip(["v1", "v2", "v3"]) {
+                                let index = required(&element, name)?.parse::<usize>()?;
+                                *vertex = *vertices
+                                    .get(index)
+                                    .context("Triangle vertex index out of bounds")?;
+                            }
+                            object.triangles.push(triangle);
+                        }
+                        b"component" => {
+                            let component = (required(&element, "objectid")?, transform(&element)?);
+                            current_object(&mut objects, &current)?
+                                .components
+                                .push(component);
+                        }
+                        b"item" => {
+                            items.push((required(&element, "objectid")?, transform(&element)?))
+                        }
+                        _ => {}
+                    }
+                }
+                Event::End(element) if element.local_name().as_ref() == b"object" => current = None,
+                Event::Eof => break,
+                _ => {}
+            }
+        }
+
+        items
+            .iter()
+            .map(|(id, transform)| {
+                let mut mesh = Mesh {
+                    triangles: resolve(&objects, id, 0)?,
+                };
+                mesh.transform_3mf(transform);
+                mesh.scale(scale);
+                Ok(mesh)
+            })
+            .collect()
+    }
+
+    fn transform_3mf(&mut self, m: &Transform) {
+        for vertex in self.triangles.iter_mut().flatten() {
+            let [x, y, z] = *vertex;
+            *vertex = [0, 1, 2].map(|i| x * m[i] + y * m[3 + i] + z * m[6 + i] + m[9 + i]);
+        }
+    }
+}
+
+/// Collects the triangles of an object and all its components.
+fn resolve(
+    objects: &HashMap<String, Object>,
+    id: &str,
+    depth: usize,
+) -> Result<Vec<[[f32; 3]; 3]>> {
+    if depth > 32 {
+        bail!("Object components are nested too deeply");
+    }
+
+    let object = objects
+        .get(id)
+        .with_context(|| format!("Unknown object `{id}`"))?;
+    let mut triangles = object.triangles.clone();
+    for (child, transform) in &object.components {
+        let mut mesh = Mesh {
+            triangles: resolve(objects, child, depth + 1)?,
+        };
+        mesh.transform_3mf(transform);
+        triangles.extend(mesh.triangles);
+    }
+
+    Ok(triangles)
+}
+
+fn current_object<'a>(
+    objects: &'a mut HashMap<String, Object>,
+    current: &Option<String>,
+) -> Result<&'a mut Object> {
+    let id = current.as_ref().context("Mesh data outside of an object")?;
+    Ok(objects.get_mut(id).unwrap())
+}
+
+fn read_file(archive: &mut ZipArchive<Cursor<&[u8]>>, name: &str) -> Result<String> {
+    let mut out = String::new();
+    archive.by_name(name)?.read_to_string(&mut out)?;
+    Ok(out)
+}
+
+/// Finds the path of the model from the package relationships.
+fn find_model(rels: &str) -> Result<Option<String>> {
+    let mut reader = Reader::from_str(rels);
+    loop {
+        match reader.read_event()? {
+            Event::Start(element) | Event::Empty(element)
+                if element.local_name().as_ref() == b"Relationship"
+                    && attribute(&element, "Type")?.as_deref() == Some(MODEL_RELATIONSHIP) =>
+            {
+                return attribute(&element, "Target");
+            }
+            Event::Eof => return Ok(None),
+            _ => {}
+        }
+    }
+}
+
+fn attribute(element: &BytesStart, name: &str) -> Result<Option<String>> {
+    for attribute in element.attributes() {
+        let attribute = attribute?;
+        if attribute.key.local_name().as_ref() == name.as_bytes() {
+            return Ok(Some(attribute.unescape_value()?.into_owned()));
+        }
+    }
+    Ok(None)
+}
+
+fn required(element: &BytesStart, name: &str) -> Result<String> {
+    attribute(element, name)?.with_context(|| {
+        let element = String::from_utf8_lossy(element.local_name().as_ref()).into_owned();
+        format!("`{element}` is missing the `{name}` attribute")
+    })
+}
+
+fn number(element: &BytesStart, name: &str) -> Result<f32> {
+    Ok(required(element, name)?.parse()?)
+}
+
+fn transform(element: &BytesStart) -> Result<Transform> {
+    let Some(transform) = attribute(element, "transform")? else {
+        return Ok(IDENTITY);
+    };
+
+    let values = transform
+        .split_whitespace()
+        .map(|x| x.parse::<f32>())
+        .collect::<Result<Vec<_>, _>>()?;
+    values
+        .try_into()
+        .map_err(|x: Vec<f32>| anyhow::anyhow!("Transform has {} values instead of 12", x.len()))
+}
+
+/// The size of a unit in mm.
+fn unit_scale(unit: &str) -> Result<f32> {
+    Ok(match unit {
+        "micron" => 0.001,
+        "millimeter" => 1.0,
+        "centimeter" => 10.0,
+        "inch" => 25.4,
+        "foot" => 304.8,
+        "meter" => 1000.0,
+        _ => bail!("Unknown unit `{unit}`"),
+    })
+}
@@ -0,0 +1,64 @@
+//! Statistics about layers, for checking a print before sending it to the printer.
+
+use crate::{
+    encoded_layer::LayerDecoder,
+    file::File,
+    layer_content::LayerContent,
+    misc::{Bounds, Run},
+};
+
+/// Statistics about the pixels of one layer.
+#[derive(Clone, Debug)]
+pub struct LayerStats {
+    /// Number of pixels with a non-zero value.
+    pub exposed_pixels: u64,
+    /// Exposed area in mm², with each pixel weighted by its brightness.
+    pub area: f32,
+    /// Smallest rectangle containing every exposed pixel.
+    pub bounds: Option<Bounds>,
+    /// Number of pixels with each value.
+    pub histogram: [u64; 256],
+    /// Size of the encoded layer divided by the size of the raw pixels.
+    pub compression_ratio: f32,
+}
+
+impl LayerContent {
+    /// Computes statistics for this layer straight from its runs.
+    ///
+    /// `pixel_size` is the width and height of a pixel in mm.
+    pub fn stats(&self, width: u32, height: u32, pixel_size: (f32, f32)) -> LayerStats {
+        let mut histogram = [0; 256];
+        for Run { length, value } in LayerDecoder::new(&self.data) {
+            histogram[value as usize] += length;
+        }
+
+        let exposed_pixels = histogram[1..].iter().sum();
+        let weighted = (1..256)
+            .map(|i| histogram[i] as f64 * i as f64 / 255.0)
+            .sum::<f64>();
+        let pixels = width as u64 * height as u64;
+
+        LayerStats {
+            exposed_pixels,
+            area: (weighted * (pixel_size.0 * pixel_size.1) as f64) as f32,
+            bounds: self.bounds(width, height),
+            histogram,
+            compression_ratio: match pixels {
+                0 => 0.0,
+                _ => self.data.len() as f32 / pixels as f32,
+            },
+        }
+    }
+}
+
+impl File {
+    /// Computes statistics for every layer.
+    pub fn stats(&self) -> Vec<LayerStats> {
+        let (width, height) = self.resolution();
+        let pixel_size = self.pixel_size();
+        self.layers
+            .iter()
+            .map(|x| x.stats(width, height, pixel_size))
+            .collect()
+    }
+}
@@ -0,0 +1,140 @@
+use std::{fs, path::Path};
+
+use anyhow::{ensure, Context, Result};
+use image::RgbaImage;
+
+use crate::{
+    file::File,
+    header_info::HeaderInfo,
+    ini::{Ini, Section},
+    layer_content::LayerContent,
+    preview_image::PreviewImage,
+};
+
+const MANIFEST: &str = "manifest.ini";
+const SMALL_PREVIEW: &str = "small_preview.png";
+const BIG_PREVIEW: &str = "big_preview.png";
+
+fn layer_file(layer: usize) -> String {
+    format!("layer_{layer:05}.png")
+}
+
+fn layer_section(layer: usize) -> String {
+    format!("layer.{layer}")
+}
+
+impl File {
+    /// Writes this file into `dir` as a directory of PNGs and a manifest.
+    ///
+    /// Every layer is saved as a grayscale `layer_NNNNN.png`, the previews as `small_preview.png` and `big_preview.png`.
+    /// The header and the parameters of each layer are written to `manifest.ini`, which can be edited by hand.
+    /// Use [`File::pack`] to turn the directory back into a `.goo` file.
+    pub fn unpack(&self, dir: impl AsRef<Path>) -> Result<()> {
+        let dir = dir.as_ref();
+        fs::create_dir_all(dir)?;
+
+        let (width, height) = (
+            self.header.x_resolution as u32,
+            self.header.y_resolution as u32,
+        );
+
+        let mut manifest = Ini::default();
+        let mut header = Section::new("header");
+        for &field in HeaderInfo::FIELDS {
+            header.set(field, self.header.get_field(field).unwrap());
+        }
+        manifest.push(header);
+
+        for (i, layer) in self.layers.iter().enumerate() {
+            let mut section = Section::new(layer_section(i));
+            for &field in LayerContent::FIELDS {
+                section.set(field, layer.get_field(field).unwrap());
+            }
+            manifest.push(section);
+
+            layer
+                .to_image(width, height)
+                .save(dir.join(layer_file(i)))?;
+        }
+
+        self.header
+            .small_preview
+            .to_image()
+            .save(dir.join(SMALL_PREVIEW))?;
+        self.header
+            .big_preview
+            .to_image()
+            .save(dir.join(BIG_PREVIEW))?;
+        fs::write(dir.join(MANIFEST), manifest.to_string())?;
+
+        Ok(())
+    }
+
+    /// Rebuilds a file from a directory written by [`File::unpack`].
+    ///
+    /// Fields missing from the manifest keep their default values and missing previews are left empty.
+    /// Unchanged files pack back to the same pixels, parameters and string bytes, although layers written by other slicers may end up with a different (but equivalent) run encoding.
+    pub fn pack(dir: impl AsRef<Path>) -> Result<Self> {
+        let dir = dir.as_ref();
+        let manifest = fs::read_to_string(dir.join(MANIFEST))
+            .with_context(|| format!("Failed to read {MANIFEST}"))?;
+        let manifest = Ini::parse(&manifest)?;
+
+        let mut header = HeaderInfo::default();
+        if let Some(section) = manifest.section("header") {
+            for (key, value) in &section.entries {
+                header.set_field(key, value)?;
+            }
+        }
+
+        header.small_preview = load_preview(&dir.join(SMALL_PREVIEW))?;
+        header.big_preview = load_preview(&dir.join(BIG_PREVIEW))?;
+
+        let (width, height) = (header.x_resolution as u32, header.y_resolution as u32);
+        let mut layers = Vec::with_capacity(header.layer_count as usize);
+        for i in 0..header.layer_count as usize {
+            let mut layer = LayerContent::default();
+            if let Some(section) = manifest.section(&layer_section(i)) {
+                for (key, value) in &section.entries {
+                    layer.set_field(key, value)?;
+                }
+            }
+
+            let path = dir.join(layer_file(i));
+            let image = image::open(&path)
+                .with_context(|| format!("Failed to open {}", path.display()))?
+                .into_luma8();
+            ensure!(
+                image.dimensions() == (width, height),
+                "{} is {}x{}, expected {width}x{height}",
+                path.display(),
+                image.width(),
+                image.height()
+            );
+
+            layer.set_from_image(&image);
+            layers.push(layer);
+        }
+
+        Ok(Self::new(header, layers))
+    }
+}
+
+fn load_preview<const WIDTH: usize, const HEIGHT: usize>(
+    path: &Path,
+) -> Result<PreviewImage<WIDTH, HEIGHT>> {
+    if !path.exists() {
+        return Ok(PreviewImage::empty());
+    }
+
+    let image: RgbaImage = image::open(path)?.into_rgba8();
+    ensure!(
+        image.dimensions() == (WIDTH as u32, HEIGHT as u32),
+        "{} is {}x{}, expected {WIDTH}x{HEIGHT}",
+        path.display(),
+        image.width(),
+        image.height()
+    );
+
+    Ok(PreviewImage::from_image(&image))
+}
@@ -0,0 +1,90 @@
+use goo::{GooFile, HeaderInfo};
+
+mod common;
+
+const WIDTH: u32 = 8;
+const HEIGHT: u32 = 8;
+
+/// Builds a file from layers drawn as strings, with `#` for exposed pixels.
+fn file_from_rows(layers: &[&[&str]]) -> GooFile {
+    let header = HeaderInfo {
+        layer_count: layers.len() as u32,
+        ..common::header(WIDTH, HEIGHT, 0.5)
+    };
+
+    let layers = layers
+        .iter()
+        .map(|rows| common::draw(WIDTH, HEIGHT, rows))
+        .collect::<Vec<_>>();
+    common::file(header, &layers)
+}
+
+#[test]
+fn islands() {
+    let file = file_from_rows(&[
+        &["##......", "##......"],
+        &[
+            ".##.....", // Overlaps the layer below
+            "........", "........", "....##..", // Island
+            "....##..", "........", ".......#", // Diagonal pixels are one island
+            "......#.",
+        ],
+    ]);
+
+    let islands = file.islands();
+    assert_eq!(islands.len(), 2);
+    assert!(islands.iter().all(|x| x.layer == 1));
+
+    assert_eq!(islands[0].pixels, 4);
+    assert_eq!(islands[0].centroid, (2.5, 2.0));
+    assert_eq!(islands[0].area, 1.0);
+    assert_eq!(islands[1].pixels, 2);
+}
+
+#[test]
+fn resin_traps() {
+    let solid: &[&str] = &["........", ".######.", ".######.", ".######.", ".######."];
+    let hollow: &[&str] = &["........", ".######.", ".#..#...", ".#..#.#.", ".######."];
+    let vented: &[&str] = &["........", ".######.", ".##.###.", ".######.", ".######."];
+
+    // The right hollow is open to the side, the left one is sealed
+    let file = file_from_rows(&[solid, hollow, hollow, solid, solid]);
+    let traps = file.resin_traps();
+    assert_eq!(traps.len(), 1);
+    assert_eq!(traps[0].layers, (1, 2));
+    assert_eq!(traps[0].centroid.0, 1.5);
+    assert!((traps[0].volume - 8.0 * 0.25 * file.header.layer_thickness).abs() < 1e-6);
+
+    // Sealed by the build plate, but drained through the top
+    let file = file_from_rows(&[hollow, hollow, vented, solid]);
+    assert_eq!(file.resin_traps().len(), 1);
+    let file = file_from_rows(&[hollow, hollow, vented]);
+    assert!(file.resin_traps().is_empty());
+}
+
+#[test]
+fn peel_forces() {
+    let file = file_from_rows(&[
+        &["##......", "##......"],
+        &["####....", "####....", "####....", "####...."],
+        &["####....", "####....", "####....", "####...."],
+        &["#####...", "#####...", "#####...", "#......."],
+    ]);
+
+    let forces = file.peel_forces();
+    assert_eq!(forces[0].area, 1.0);
+    assert_eq!(forces[0].perimeter, 4.0);
+    assert_eq!(forces[1].area, 4.0);
+    assert_eq!(forces[1].perimeter, 8.0);
+    assert_eq!(forces[1].area_change, 3.0);
+    assert_eq!(forces[0].force, 0.125);
+    assert_eq!(forces[1].force, 1.0);
+
+    // Same area, but a longer outline
+    assert_eq!(forces[3].area_change, 0.0);
+    assert_eq!(forces[3].perimeter_change, 1.0);
+
+    let risks = file.peel_risks(0.5);
+    let layers = risks.iter().map(|x| x.layer).collect::<Vec<_>>();
+    assert_eq!(layers, [1, 3]);
+}
@@ -0,0 +1,95 @@
+#![cfg(feature = "cli")]
+
+use std::{env, fs, path::Path, process::Command};
+
+use goo::{serde::DynamicSerializer, GooFile, HeaderInfo, LayerContent};
+
+fn goo(args: &[&str]) -> (i32, String) {
+    let output = Command::new(env!("CARGO_BIN_EXE_goo"))
+        .args(args)
+        .output()
+        .unwrap();
+    (
+        output.status.code().unwrap(),
+        String::from_utf8(output.stdout).unwrap(),
+    )
+}
+
+fn write(goo: &GooFile, path: &Path) {
+    let mut ser = DynamicSerializer::new();
+    goo.serialize(&mut ser);
+    fs::write(path, ser.into_inner()).unwrap();
+}
+
+#[test]
+fn cli() -> anyhow::Result<()> {
+    let header = HeaderInfo {
+        x_resolution: 4,
+        y_resolution: 4,
+        layer_count: 3,
+        bottom_layers: 1,
+        ..Default::default()
+    };
+    let layers = (0..3)
+        .map(|i| {
+            let mut layer = LayerContent {
+                layer_position_z: 0.05 * (i + 1) as f32,
+                ..Default::default()
+            };
+            let mut pixels = [0; 16];
+            pixels[5] = 255;
+            layer.set_pixels(4, 4, &pixels);
+            layer
+        })
+        .collect();
+
+    let dir = env::temp_dir().join(format!("goo_cli_{}", std::process::id()));
+    fs::create_dir_all(&dir)?;
+    let (old, new) = (dir.join("old.goo"), dir.join("new.goo"));
+    let (old, new) = (old.to_str().unwrap(), new.to_str().unwrap());
+    write(&GooFile::new(header, layers), Path::new(old));
+
+    assert_eq!(goo(&["validate", old]).0, 0);
+
+    assert_eq!(
+        goo(&["set", old, "exposure_time=3.5", "-o", new]),
+        (0, format!("Wrote 3 layers to `{new}`\n"))
+    );
+    let (code, output) = goo(&["set", new, "--layers", "1..=2", "light_pwm=128", "--json"]);
+    assert_eq!(code, 0);
+    let output = serde_json::from_str::<serde_json::Value>(&output)?;
+    assert_eq!(output["layers"], 3);
+    assert_eq!(output["path"], new);
+    assert_eq!(goo(&["set", new, "not_a_field=1"]).0, 2);
+
+    let (code, info) = goo(&["info", new, "--json"]);
+    assert_eq!(code, 0);
+    let info = serde_json::from_str::<serde_json::Value>(&info)?;
+    assert_eq!(info["header"]["exposure_time"], 3.5);
+    assert_eq!(info["layers"], 3);
+
+    let (code, diff) = goo(&["diff", old, new, "--json"]);
+    assert_eq!(code, 1);
+    let diff = serde_json::from_str::<serde_json::Value>(&diff)?;
+    assert_eq!(diff["header"][0]["name"], "exposure_time");
+    assert_eq!(diff["layers"].as_array().unwrap().len(), 2);
+    assert_eq!(goo(&["diff", old, old]), (0, "Files are the same\n".into()));
+    let (code, diff) = goo(&["diff", old, new]);
+    assert_eq!(code, 1);
+    assert!(diff.starts_with("exposure_time: 3 -> 3.5\n2 differing layers\n"));
+
+    let ctb = dir.join("converted.ctb");
+    let ctb = ctb.to_str().unwrap();
+    assert_eq!(goo(&["convert", old, ctb]).0, 0);
+    assert_eq!(goo(&["stats", ctb, "--json"]).0, 0);
+
+    // Truncated files are reported as errors rather than panics
+    let data = fs::read(old)?;
+    fs::write(old, &data[..data.len() / 2])?;
+    let (code, output) = goo(&["validate", old, "--json"]);
+    assert_eq!(code, 2);
+    assert!(serde_json::from_str::<serde_json::Value>(&output)?["error"].is_string());
+
+    fs::remove_dir_all(&dir)?;
+    Ok(())
+}
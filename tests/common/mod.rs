@@ -0,0 +1,50 @@
+//! Builders for the small files used across the integration tests.
+
+// Each test crate only uses some of these
+#![allow(dead_code)]
+
+use goo::{GooFile, HeaderInfo, LayerContent};
+
+/// A header for a `width` by `height` image with square pixels `pixel_size` mm wide.
+pub fn header(width: u32, height: u32, pixel_size: f32) -> HeaderInfo {
+    HeaderInfo {
+        x_resolution: width as u16,
+        y_resolution: height as u16,
+        x_size: width as f32 * pixel_size,
+        y_size: height as f32 * pixel_size,
+        ..Default::default()
+    }
+}
+
+/// Builds a file with a layer for each image, stacked one layer thickness apart.
+pub fn file<T: AsRef<[u8]>>(header: HeaderInfo, layers: &[T]) -> GooFile {
+    let (width, height) = (header.x_resolution as u32, header.y_resolution as u32);
+
+    let layers = layers
+        .iter()
+        .enumerate()
+        .map(|(i, pixels)| {
+            let mut layer = LayerContent {
+                layer_position_z: header.layer_thickness * (i + 1) as f32,
+                ..Default::default()
+            };
+            layer.set_pixels(width, height, pixels.as_ref());
+            layer
+        })
+        .collect();
+
+    GooFile::new(header, layers)
+}
+
+/// Draws a layer image from rows of text, with `#` for exposed pixels.
+pub fn draw(width: u32, height: u32, rows: &[&str]) -> Vec<u8> {
+    let mut pixels = vec![0; (width * height) as usize];
+    for (y, row) in rows.iter().enumerate() {
+        for (x, c) in row.chars().enumerate() {
+            if c == '#' {
+                pixels[y * width as usize + x] = 255;
+            }
+        }
+    }
+    pixels
+}
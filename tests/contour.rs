@@ -0,0 +1,49 @@
+use goo::GooFile;
+
+mod common;
+
+const SIZE: u32 = 8;
+
+fn file(rows: &[&str]) -> GooFile {
+    common::file(
+        common::header(SIZE, SIZE, 0.5),
+        &[common::draw(SIZE, SIZE, rows)],
+    )
+}
+
+#[test]
+fn contours_with_holes() {
+    let file = file(&[
+        "........", ".#####..", ".#...#..", ".#...#..", ".#####..", "........", "......#.",
+        "........",
+    ]);
+
+    let contours = file.contours(0);
+    assert_eq!(contours.len(), 3);
+
+    let holes = contours.iter().filter(|x| x.is_hole()).count();
+    assert_eq!(holes, 1);
+
+    // The outline runs through the middle of the edge pixels, cutting the corners
+    let outline = contours
+        .iter()
+        .find(|x| x.points.len() == 8 && !x.is_hole())
+        .unwrap();
+    assert_eq!(outline.points.len(), 8);
+    assert!((outline.area() - (5.0 * 4.0 - 0.5) * 0.25).abs() < 1e-5);
+    assert!(outline.points.contains(&(0.5, 0.75)));
+
+    let simplified = outline.simplify(0.5);
+    assert_eq!(simplified.points.len(), 4);
+}
+
+#[test]
+fn layer_svg() {
+    let file = file(&["##", "##"]);
+    let svg = file.layer_svg(0, None);
+
+    assert!(svg.starts_with(r#"<svg xmlns="http://www.w3.org/2000/svg" width="4mm" height="4mm""#));
+    assert!(
+        svg.contains(r#"d="M0.75 0 L1 0.25 L1 0.75 L0.75 1 L0.25 1 L0 0.75 L0 0.25 L0.25 0 Z""#)
+    );
+}
@@ -0,0 +1,85 @@
+use goo::{diff::PixelDiff, fields::Value, misc::Bounds, GooFile, HeaderInfo};
+
+mod common;
+
+const WIDTH: u32 = 4;
+const HEIGHT: u32 = 4;
+
+fn file(layers: &[&[u8]]) -> GooFile {
+    let header = HeaderInfo {
+        x_resolution: WIDTH as u16,
+        y_resolution: HEIGHT as u16,
+        ..Default::default()
+    };
+    common::file(header, layers)
+}
+
+#[test]
+fn diff() {
+    let mut pixels = [0; 16];
+    pixels[5] = 255;
+    pixels[6] = 128;
+    let old = file(&[&pixels, &pixels]);
+
+    pixels[5] = 0;
+    pixels[6] = 255;
+    pixels[11] = 255;
+    let mut new = file(&[&[0; 16], &pixels, &pixels]);
+    new.header.exposure_time += 0.001;
+    new.header.bottom_layers += 1;
+    new.layers[1].layer_exposure_time = 10.0;
+
+    assert!(old.diff(&old, 0.0).is_empty());
+
+    let diff = old.diff(&new, 0.01);
+    assert_eq!(diff.layer_count, (2, 3));
+    assert_eq!(diff.header.len(), 1);
+    assert_eq!(diff.header[0].name, "bottom_layers");
+    assert_eq!(
+        (&diff.header[0].old, &diff.header[0].new),
+        (&Value::U32(8), &Value::U32(9))
+    );
+    assert_eq!(old.diff(&new, 0.0).header.len(), 2);
+
+    assert_eq!(diff.layers.len(), 2);
+    assert!(diff.layers[0].fields.is_empty());
+    assert_eq!(diff.layers[0].pixels.as_ref().unwrap().removed, 2);
+
+    let layer = &diff.layers[1];
+    assert_eq!(layer.layer, 1);
+    assert_eq!(layer.fields.len(), 1);
+    assert_eq!(layer.fields[0].name, "layer_exposure_time");
+    assert_eq!(
+        layer.pixels,
+        Some(PixelDiff {
+            added: 1,
+            removed: 1,
+            changed: 1,
+            bounds: Some(Bounds {
+                min: (1, 1),
+                max: (4, 3)
+            }),
+        })
+    );
+
+    assert_eq!(old.diff(&old, 0.0).to_string(), "Files are the same\n");
+    assert_eq!(
+        diff.to_string(),
+        "\
+Layer count: 2 -> 3
+bottom_layers: 8 -> 9
+2 differing layers
+  layer 0
+    pixels: +0 -2 ~0 in (1, 1)..(3, 2)
+  layer 1
+    layer_exposure_time: 50 -> 10
+    pixels: +1 -1 ~1 in (1, 1)..(4, 3)
+"
+    );
+
+    let image = old.diff_image(&new, 1);
+    assert_eq!(image.get_pixel(1, 1).0, [255, 0, 0]);
+    assert_eq!(image.get_pixel(2, 1).0, [255, 255, 0]);
+    assert_eq!(image.get_pixel(3, 2).0, [0, 255, 0]);
+    assert_eq!(image.get_pixel(0, 0).0, [0, 0, 0]);
+}
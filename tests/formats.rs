@@ -0,0 +1,412 @@
+use goo::{GooFile, HeaderInfo};
+
+mod common;
+
+const WIDTH: u32 = 6;
+const HEIGHT: u32 = 4;
+
+fn pixels(layer: u32) -> Vec<u8> {
+    (0..WIDTH * HEIGHT)
+        .map(|i| {
+            if (i + layer).is_multiple_of(3) {
+                255
+            } else {
+                0
+            }
+        })
+        .collect()
+}
+
+fn example_file() -> GooFile {
+    let header = HeaderInfo {
+        layer_count: 5,
+        bottom_layers: 2,
+        exposure_time: 2.5,
+        bottom_exposure_time: 30.0,
+        ..common::header(WIDTH, HEIGHT, 0.05)
+    };
+
+    let mut file = common::file(header, &(0..5).map(pixels).collect::<Vec<_>>());
+    for (i, layer) in file.layers.iter_mut().enumerate() {
+        layer.layer_exposure_time = if i < 2 { 30.0 } else { 2.5 };
+    }
+    file
+}
+
+fn assert_same_layers(a: &GooFile, b: &GooFile) {
+    assert_eq!(a.header.x_resolution, b.header.x_resolution);
+    assert_eq!(a.header.y_resolution, b.header.y_resolution);
+    assert_eq!(a.header.bottom_layers, b.header.bottom_layers);
+    assert_eq!(a.layers.len(), b.layers.len());
+
+    for (a, b) in a.layers.iter().zip(&b.layers) {
+        assert_eq!(a.layer_exposure_time, b.layer_exposure_time);
+        assert!((a.layer_position_z - b.layer_position_z).abs() < 1e-5);
+        assert_eq!(
+            a.decode_pixels(WIDTH, HEIGHT),
+            b.decode_pixels(WIDTH, HEIGHT)
+        );
+    }
+}
+
+#[test]
+fn goo_truncated() {
+    use goo::serde::DynamicSerializer;
+
+    let mut serializer = DynamicSerializer::new();
+    example_file().serialize(&mut serializer);
+    let bytes = serializer.into_inner();
+
+    // Cut inside the header and previews, then all through the layers
+    assert!(GooFile::deserialize(&bytes).is_ok());
+    let layers = bytes.len() - 400;
+    for length in (0..layers).step_by(4999).chain(layers..bytes.len()) {
+        assert!(GooFile::deserialize(&bytes[..length]).is_err());
+    }
+}
+
+#[test]
+fn damaged_layer_data() {
+    use goo::{misc::Run, LayerDecoder};
+
+    let layer = &example_file().layers[0];
+    for length in 0..layer.data.len() {
+        let mut decoder = LayerDecoder::new(&layer.data[..length]);
+        let pixels = decoder
+            .by_ref()
+            .map(|Run { length, .. }| length)
+            .sum::<u64>();
+        assert!(decoder.is_damaged() || pixels < (WIDTH * HEIGHT) as u64);
+    }
+
+    // A gray run followed by a diff that would go past white
+    let mut decoder = LayerDecoder::new(&[0x41, 0xF5, 0x8F]);
+    assert_eq!(decoder.next().map(|x| x.value), Some(0xF5));
+    assert!(decoder.next().is_none());
+    assert!(decoder.is_damaged());
+
+    let mut decoder = LayerDecoder::new(&layer.data);
+    decoder.by_ref().for_each(drop);
+    assert!(!decoder.is_damaged());
+}
+
+#[cfg(feature = "sl1")]
+#[test]
+fn sl1_roundtrip() -> anyhow::Result<()> {
+    use goo::formats::sl1;
+
+    let goo = example_file();
+    let bytes = sl1::File::from_goo(&goo)?.serialize()?;
+    let sl1 = sl1::File::deserialize(&bytes)?;
+
+    assert_eq!(sl1.resolution(), (WIDTH, HEIGHT));
+    assert_eq!(sl1.fade_layers, 2);
+    assert_same_layers(&goo, &sl1.to_goo()?);
+    Ok(())
+}
+
+#[cfg(feature = "sl1")]
+#[test]
+fn sl1_layer_count_from_images() -> anyhow::Result<()> {
+    use std::io::{Cursor, Read, Write};
+
+    use goo::formats::sl1;
+    use zip::{write::SimpleFileOptions, ZipArchive, ZipWriter};
+
+    // `numFast` and `numSlow` count tilt speeds, not layers, so they must not change the layer count
+    let goo = example_file();
+    let bytes = sl1::File::from_goo(&goo)?.serialize()?;
+    let mut archive = ZipArchive::new(Cursor::new(bytes))?;
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    for i in 0..archive.len() {
+        let mut file = archive.by_index(i)?;
+        let mut data = Vec::new();
+        file.read_to_end(&mut data)?;
+        if file.name() == "config.ini" {
+            assert!(!String::from_utf8_lossy(&data).contains("numFast"));
+            data.extend_from_slice(b"numFast = 1\nnumSlow = 0\n");
+        }
+        zip.start_file(file.name(), SimpleFileOptions::default())?;
+        zip.write_all(&data)?;
+    }
+
+    let sl1 = sl1::File::deserialize(&zip.finish()?.into_inner())?;
+    assert_same_layers(&goo, &sl1.to_goo()?);
+    Ok(())
+}
+
+#[test]
+fn ctb_roundtrip() -> anyhow::Result<()> {
+    use goo::formats::ctb::{self, Kind};
+
+    let goo = example_file();
+    for kind in [Kind::Ctb, Kind::Cbddlp] {
+        let bytes = ctb::File::from_goo(&goo, kind).serialize();
+        let ctb = ctb::File::deserialize(&bytes)?;
+
+        assert_eq!(ctb.kind, kind);
+        assert_eq!(ctb.resolution(), (WIDTH, HEIGHT));
+        assert_same_layers(&goo, &ctb.to_goo()?);
+    }
+
+    Ok(())
+}
+
+/// A two layer 4x2 CTBv3 file assembled by hand from the documented byte layout.
+fn ctb_fixture() -> Vec<u8> {
+    fn u32s(out: &mut Vec<u8>, values: &[u32]) {
+        values.iter().for_each(|x| out.extend(x.to_le_bytes()));
+    }
+    fn f32s(out: &mut Vec<u8>, values: &[f32]) {
+        values.iter().for_each(|x| out.extend(x.to_le_bytes()));
+    }
+
+    let layer_data: [&[u8]; 2] = [&[0xFF, 0x08], &[0x80, 0x04, 0x40, 0xFF, 0x03]];
+    let mut out = Vec::new();
+
+    // Header: magic, version, bed size, two unknown words
+    u32s(&mut out, &[0x12FD0086, 3]);
+    f32s(&mut out, &[68.04, 120.96, 150.0, 0.0, 0.0]);
+    // Total height, layer height, exposure, bottom exposure, light off delay
+    f32s(&mut out, &[0.1, 0.05, 2.0, 20.0, 1.0]);
+    // Bottom layers, resolution, large preview, layer table, layer count, small preview
+    u32s(&mut out, &[1, 4, 2, 0, 252, 2, 0]);
+    // Print time, mirrored, print parameters offset and size, anti-aliasing
+    u32s(&mut out, &[90, 0, 112, 60, 1]);
+    // Light PWM, bottom light PWM, encryption key, slicer info offset and size
+    out.extend([255, 0, 200, 0]);
+    u32s(&mut out, &[0, 172, 76]);
+    assert_eq!(out.len(), 112);
+
+    // Print parameters: lift, speeds, volume, weight, cost, light off delays, bottom layers
+    f32s(
+        &mut out,
+        &[6.0, 60.0, 5.0, 90.0, 150.0, 0.0, 0.0, 0.0, 1.0, 1.0],
+    );
+    u32s(&mut out, &[1, 0, 0, 0, 0]);
+    assert_eq!(out.len(), 172);
+
+    // Slicer info: second lift and retract, rest after lift, machine name, flags
+    f32s(&mut out, &[0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.5]);
+    u32s(&mut out, &[248, 4]);
+    out.extend([0, 0, 0, 0]);
+    u32s(&mut out, &[0, 1, 0]);
+    f32s(&mut out, &[0.5, 0.0]);
+    u32s(&mut out, &[0, 0, 0, 0]);
+    assert_eq!(out.len(), 248);
+    out.extend(b"Tiny");
+
+    // Layer table: z, exposure, light off delay, address, size, page, table size, padding
+    let mut address = 252 + 2 * 36;
+    for (i, data) in layer_data.iter().enumerate() {
+        let z = 0.05 * (i + 1) as f32;
+        f32s(&mut out, &[z, [20.0, 2.0][i], 1.0]);
+        u32s(&mut out, &[address + 48, data.len() as u32, 0, 84, 0, 0]);
+        address += 48 + data.len() as u32;
+    }
+
+    // Layers: extended parameters followed by the image runs
+    for (i, data) in layer_data.iter().enumerate() {
+        u32s(&mut out, &[48]);
+        let lift = [6.0, 5.0][i];
+        f32s(
+            &mut out,
+            &[
+                lift, 60.0, 1.5, 120.0, 150.0, 0.5, 30.0, 0.0, 0.5, 0.5, 255.0,
+            ],
+        );
+        out.extend(*data);
+    }
+
+    out
+}
+
+#[test]
+fn ctb_fixture_decodes() -> anyhow::Result<()> {
+    use goo::formats::ctb::{self, Kind};
+
+    let ctb = ctb::File::deserialize(&ctb_fixture())?;
+    assert_eq!(ctb.kind, Kind::Ctb);
+    assert_eq!(ctb.version, 3);
+    assert_eq!(ctb.resolution(), (4, 2));
+    assert_eq!(ctb.header.bottom_layers, 1);
+    assert_eq!(ctb.header.light_pwm, 255);
+    assert_eq!(ctb.header.bottom_light_pwm, 200);
+    assert_eq!(ctb.machine_name, "Tiny");
+    assert_eq!(ctb.print_parameters.lift_height, 5.0);
+    assert_eq!(ctb.layers.len(), 2);
+
+    assert_eq!(ctb.layers[0].exposure_time, 20.0);
+    assert_eq!(ctb.layers[0].lift_height, 6.0);
+    assert_eq!(ctb.layers[1].position_z, 0.1);
+    assert_eq!(ctb.layers[1].lift_height, 5.0);
+    assert_eq!(ctb.layers[1].lift_height_2, 1.5);
+    assert_eq!(ctb.layers[1].retract_height_2, 0.5);
+
+    assert_eq!(ctb.decode_pixels(0)?, [255; 8]);
+    assert_eq!(ctb.decode_pixels(1)?, [0, 0, 0, 0, 128, 255, 255, 255]);
+
+    let goo = ctb.to_goo()?;
+    assert_eq!(
+        goo.layers[1].decode_pixels(4, 2),
+        [0, 0, 0, 0, 128, 255, 255, 255]
+    );
+    Ok(())
+}
+
+#[test]
+fn ctb_layers_are_padded_and_cut_to_size() -> anyhow::Result<()> {
+    use goo::formats::ctb;
+
+    let mut ctb = ctb::File::deserialize(&ctb_fixture())?;
+    ctb.layers[0].data[0] = vec![0xFF, 0x03];
+    ctb.layers[1].data[0] = vec![0xFF, 0x20];
+
+    let goo = ctb.to_goo()?;
+    assert_eq!(
+        goo.layers[0].decode_pixels(4, 2),
+        [255, 255, 255, 0, 0, 0, 0, 0]
+    );
+    assert_eq!(goo.layers[1].decode_pixels(4, 2), [255; 8]);
+    Ok(())
+}
+
+#[test]
+fn ctb_bad_layer_address() {
+    use goo::formats::ctb;
+
+    let mut bytes = ctb_fixture();
+    // Point the first layer's data inside the extended parameter gap at the start of the file
+    bytes[252 + 12..252 + 16].copy_from_slice(&10u32.to_le_bytes());
+    assert!(ctb::File::deserialize(&bytes).is_err());
+}
+
+#[test]
+fn ctb_untrusted_header() {
+    use goo::formats::ctb;
+
+    let patched = |offset: usize, value: u32| {
+        let mut bytes = ctb_fixture();
+        bytes[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+        bytes
+    };
+
+    // Huge layer counts, resolutions and offsets are errors rather than huge allocations or overflows
+    assert!(ctb::File::deserialize(&patched(68, 0xFFFF_FFF0)[..112]).is_err());
+    assert!(ctb::File::deserialize(&patched(68, 0xFFFF_FFF0)).is_err());
+    assert!(ctb::File::deserialize(&patched(52, 0xFFFF_FFFF)).is_err());
+    assert!(ctb::File::deserialize(&patched(64, 0xFFFF_FFF0)).is_err());
+
+    let mut cbddlp = patched(92, 0xFFFF_FFFF);
+    cbddlp[..4].copy_from_slice(&0x12FD0019u32.to_le_bytes());
+    assert!(ctb::File::deserialize(&cbddlp).is_err());
+}
+
+#[test]
+fn ctb_encrypted_layers() -> anyhow::Result<()> {
+    use goo::formats::ctb::{self, Kind};
+
+    let goo = example_file();
+    let mut ctb = ctb::File::from_goo(&goo, Kind::Ctb);
+    let plain = ctb.serialize();
+
+    ctb.header.encryption_key = 0x1234_5678;
+    let encrypted = ctb.serialize();
+    assert_eq!(plain.len(), encrypted.len());
+    assert_ne!(plain, encrypted);
+
+    let decrypted = ctb::File::deserialize(&encrypted)?;
+    assert_eq!(decrypted.header.encryption_key, 0x1234_5678);
+    assert_same_layers(&goo, &decrypted.to_goo()?);
+    Ok(())
+}
+
+#[test]
+fn anycubic_roundtrip() -> anyhow::Result<()> {
+    use goo::formats::anycubic::{self, VERSION_PWMX, VERSION_PWS};
+
+    let goo = example_file();
+    for version in [VERSION_PWS, VERSION_PWMX] {
+        let bytes = anycubic::File::from_goo(&goo, version).serialize();
+        let anycubic = anycubic::File::deserialize(&bytes)?;
+
+        assert_eq!(anycubic.version, version);
+        assert_eq!(anycubic.resolution(), (WIDTH, HEIGHT));
+        assert_same_layers(&goo, &anycubic.to_goo());
+    }
+
+    Ok(())
+}
+
+#[test]
+fn anycubic_untrusted_header() -> anyhow::Result<()> {
+    use goo::formats::anycubic::{self, VERSION_PWMX};
+
+    let bytes = anycubic::File::from_goo(&example_file(), VERSION_PWMX).serialize();
+    let read_u32 =
+        |offset: usize| u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap());
+    let patched = |offset: usize, value: u32| {
+        let mut bytes = bytes.clone();
+        bytes[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+        bytes
+    };
+
+    // Offsets of the sections from the file mark, each section starts with a 12 byte name and its length
+    let (header, preview, layer_def) = (
+        read_u32(20) as usize,
+        read_u32(28) as usize,
+        read_u32(36) as usize,
+    );
+    assert!(anycubic::File::deserialize(&patched(header + 16 + 44, 0xFFFF_FFFF)).is_err());
+    assert!(anycubic::File::deserialize(&patched(preview + 16, 0xFFFF_FFFF)).is_err());
+    assert!(anycubic::File::deserialize(&patched(layer_def + 16, 0xFFFF_FFF0)).is_err());
+    assert!(anycubic::File::deserialize(&patched(layer_def + 16, 5)).is_ok());
+    Ok(())
+}
+
+#[test]
+fn anycubic_info_from_file() -> anyhow::Result<()> {
+    use goo::{formats::anycubic, SlicedFile};
+
+    let goo = example_file();
+    let bytes = anycubic::File::from_goo(&goo, anycubic::VERSION_PWMX).serialize();
+    let anycubic = anycubic::File::deserialize(&bytes)?;
+
+    let top = goo.layers.last().unwrap().layer_position_z;
+    assert!((anycubic.info().size.2 - top).abs() < 1e-5);
+    assert!((anycubic.layer_info(4).position_z - top).abs() < 1e-5);
+    Ok(())
+}
+
+#[test]
+fn sliced_file_agrees_across_formats() -> anyhow::Result<()> {
+    use goo::{
+        formats::{anycubic, ctb},
+        SlicedFile,
+    };
+
+    let goo = example_file();
+    let files: [Box<dyn SlicedFile>; 3] = [
+        Box::new(example_file()),
+        Box::new(ctb::File::from_goo(&goo, ctb::Kind::Ctb)),
+        Box::new(anycubic::File::from_goo(&goo, anycubic::VERSION_PWMX)),
+    ];
+
+    for file in &files {
+        let info = file.info();
+        assert_eq!(info.resolution, (WIDTH, HEIGHT));
+        assert_eq!(info.bottom_layers, 2);
+        assert_eq!(file.layer_count(), 5);
+
+        for i in 0..file.layer_count() {
+            let layer = file.layer_info(i);
+            assert_eq!(layer.exposure_time, goo.layers[i].layer_exposure_time);
+            assert!((layer.position_z - goo.layers[i].layer_position_z).abs() < 1e-5);
+            assert_eq!(layer.lift_distance, 5.0);
+            assert_eq!(layer.retract_distance, layer.lift_distance);
+            assert_eq!(file.decode_layer(i)?, pixels(i as u32));
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,105 @@
+use goo::{
+    hollow::{DrainHole, HollowConfig, Infill},
+    GooFile, HeaderInfo,
+};
+
+mod common;
+
+const SIZE: u32 = 12;
+
+/// A 10mm solid cube made of 1mm pixels and layers.
+fn cube() -> GooFile {
+    let header = HeaderInfo {
+        layer_thickness: 1.0,
+        ..common::header(SIZE, SIZE, 1.0)
+    };
+
+    let mut rows = [".##########."; SIZE as usize];
+    rows[0] = "............";
+    rows[11] = "............";
+    common::file(header, &vec![common::draw(SIZE, SIZE, &rows); 10])
+}
+
+fn exposed(file: &GooFile, layer: usize) -> usize {
+    let pixels = file.layers[layer].decode_pixels(SIZE, SIZE);
+    pixels.iter().filter(|&&x| x > 0).count()
+}
+
+#[test]
+fn hollow() {
+    let mut file = cube();
+    file.hollow(&HollowConfig {
+        wall_thickness: 2.0,
+        infill: None,
+        drain_holes: vec![DrainHole {
+            position: (6.0, 6.0),
+            diameter: 2.0,
+        }],
+    });
+
+    let counts = (0..10).map(|i| exposed(&file, i)).collect::<Vec<_>>();
+    assert_eq!(counts, [96, 96, 64, 64, 64, 64, 64, 64, 100, 100]);
+
+    let pixels = file.layers[0].decode_pixels(SIZE, SIZE);
+    assert_eq!(pixels[(6 * SIZE + 6) as usize], 0);
+    assert_eq!(pixels[(6 * SIZE + 3) as usize], 255);
+}
+
+#[test]
+fn hollow_with_infill() {
+    let mut file = cube();
+    file.hollow(&HollowConfig {
+        wall_thickness: 2.0,
+        infill: Some(Infill {
+            spacing: 3.0,
+            thickness: 1.0,
+        }),
+        drain_holes: Vec::new(),
+    });
+
+    assert!((2..8).all(|i| (64..100).contains(&exposed(&file, i))));
+}
+
+#[test]
+fn infill_follows_absolute_z() {
+    let config = HollowConfig {
+        wall_thickness: 2.0,
+        infill: Some(Infill {
+            spacing: 3.0,
+            thickness: 1.0,
+        }),
+        drain_holes: Vec::new(),
+    };
+    let hollowed = |offset: f32| {
+        let mut file = cube();
+        for layer in &mut file.layers {
+            layer.layer_position_z += offset;
+        }
+        file.hollow(&config);
+        file.layers
+            .iter()
+            .map(|x| x.decode_pixels(SIZE, SIZE))
+            .collect::<Vec<_>>()
+    };
+
+    // A part starting higher up, like one made by `split_at`, continues the same lattice
+    assert_eq!(hollowed(3.0), hollowed(0.0));
+    assert_ne!(hollowed(1.0), hollowed(0.0));
+}
+
+#[test]
+fn drain_hole_under_wall() {
+    let mut file = cube();
+    file.hollow(&HollowConfig {
+        wall_thickness: 2.0,
+        infill: None,
+        drain_holes: vec![DrainHole {
+            position: (1.5, 1.5),
+            diameter: 1.0,
+        }],
+    });
+
+    // The hole never reaches the hollow, so it isn't cut at all
+    let counts = (0..10).map(|i| exposed(&file, i)).collect::<Vec<_>>();
+    assert_eq!(counts, [100, 100, 64, 64, 64, 64, 64, 64, 100, 100]);
+}
@@ -0,0 +1,227 @@
+use goo::{GooFile, HeaderInfo, LayerContent};
+
+fn example_file(layers: u32, bottom_layers: u32) -> GooFile {
+    let header = HeaderInfo {
+        layer_count: layers,
+        bottom_layers,
+        ..Default::default()
+    };
+
+    let layers = (0..layers)
+        .map(|i| LayerContent {
+            layer_position_z: header.layer_thickness * (i + 1) as f32,
+            layer_exposure_time: i as f32,
+            ..Default::default()
+        })
+        .collect();
+
+    GooFile::new(header, layers)
+}
+
+fn assert_consistent(file: &GooFile) {
+    assert_eq!(file.header.layer_count as usize, file.layers.len());
+    for (i, layer) in file.layers.iter().enumerate() {
+        let expected = file.header.layer_thickness * (i + 1) as f32;
+        assert!((layer.layer_position_z - expected).abs() < 1e-5);
+    }
+}
+
+fn exposures(file: &GooFile) -> Vec<f32> {
+    file.layers.iter().map(|x| x.layer_exposure_time).collect()
+}
+
+#[test]
+fn insert_layer() {
+    let mut file = example_file(4, 2);
+    let layer = LayerContent {
+        layer_exposure_time: 10.0,
+        ..Default::default()
+    };
+
+    file.insert_layer(1, layer).unwrap();
+    assert_consistent(&file);
+    assert_eq!(exposures(&file), [0.0, 10.0, 1.0, 2.0, 3.0]);
+    assert_eq!(file.header.bottom_layers, 3);
+
+    file.insert_layer(5, LayerContent::default()).unwrap();
+    assert_consistent(&file);
+    assert_eq!(file.header.bottom_layers, 3);
+
+    assert!(file.insert_layer(7, LayerContent::default()).is_err());
+    assert_eq!(file.layers.len(), 6);
+}
+
+#[test]
+fn remove_layers() {
+    let mut file = example_file(6, 2);
+
+    let removed = file.remove_layers(1..4).unwrap();
+    assert_eq!(removed.len(), 3);
+    assert_consistent(&file);
+    assert_eq!(exposures(&file), [0.0, 4.0, 5.0]);
+    assert_eq!(file.header.bottom_layers, 1);
+
+    assert!(file.remove_layers(2..=3).is_err());
+    assert!(file.remove_layers(4..).is_err());
+    assert_eq!(file.remove_layers(1..).unwrap().len(), 2);
+    assert_consistent(&file);
+}
+
+#[test]
+fn duplicate_layer() {
+    let mut file = example_file(3, 1);
+
+    file.duplicate_layer(2).unwrap();
+    assert_consistent(&file);
+    assert_eq!(exposures(&file), [0.0, 1.0, 2.0, 2.0]);
+    assert_eq!(file.header.bottom_layers, 1);
+
+    assert!(file.duplicate_layer(4).is_err());
+}
+
+#[test]
+fn reorder() {
+    let mut file = example_file(4, 1);
+
+    file.reorder(&[3, 1, 2, 0]).unwrap();
+    assert_consistent(&file);
+    assert_eq!(exposures(&file), [3.0, 1.0, 2.0, 0.0]);
+
+    assert!(file.reorder(&[0, 0, 1, 2]).is_err());
+    assert!(file.reorder(&[0, 1, 2]).is_err());
+}
+
+#[test]
+fn resume_from() {
+    let mut file = example_file(6, 2);
+    file.header.bottom_lift_speed = 30.0;
+
+    assert!(file.resume_from(6, 1, 40.0).is_err());
+    let resumed = file.resume_from(3, 1, 40.0).unwrap();
+    assert_consistent(&resumed);
+    assert_eq!(exposures(&resumed), [40.0, 4.0, 5.0]);
+    assert_eq!(resumed.header.bottom_layers, 1);
+    assert_eq!(resumed.header.bottom_exposure_time, 40.0);
+    assert_eq!(resumed.layers[0].lift_speed, 30.0);
+    assert_eq!(
+        resumed.header.printing_time,
+        resumed.print_time().round() as u32
+    );
+}
+
+#[test]
+fn split_at() {
+    let mut file = example_file(10, 2);
+    file.header.x_resolution = 4;
+    file.header.y_resolution = 4;
+    for (i, layer) in file.layers.iter_mut().enumerate() {
+        let mut pixels = [0; 16];
+        pixels[i % 16] = 255;
+        layer.set_pixels(4, 4, &pixels);
+    }
+
+    let thickness = file.header.layer_thickness;
+    let parts = file
+        .split_at(&[thickness * 1.5, thickness * 6.0, thickness * 20.0])
+        .unwrap();
+
+    let counts = parts.iter().map(|x| x.layers.len()).collect::<Vec<_>>();
+    assert_eq!(counts, [1, 5, 4]);
+
+    // The second original bottom layer starts the second part
+    let bottom_layers = parts.iter().map(|x| x.header.bottom_layers);
+    assert_eq!(bottom_layers.collect::<Vec<_>>(), [1, 1, 0]);
+    assert_eq!(
+        parts[1].layers[0].layer_exposure_time,
+        file.layers[1].layer_exposure_time
+    );
+
+    for part in &parts {
+        assert_eq!(part.header.layer_count as usize, part.layers.len());
+        assert_eq!(part.header.printing_time, part.print_time().round() as u32);
+    }
+    assert_eq!(parts[2].layers[0].layer_position_z, thickness * 7.0);
+
+    assert!(file.split_at(&[2.0, 1.0]).is_err());
+}
+
+#[test]
+fn split_objects() {
+    let mut file = example_file(4, 2);
+    file.header.x_resolution = 4;
+    file.header.y_resolution = 4;
+    for (i, layer) in file.layers.iter_mut().enumerate() {
+        let mut pixels = [0; 16];
+        pixels[0] = 255;
+        if i < 3 {
+            pixels[15] = 128;
+        }
+        layer.set_pixels(4, 4, &pixels);
+    }
+
+    let parts = file.split_objects().unwrap();
+    let counts = parts.iter().map(|x| x.layers.len()).collect::<Vec<_>>();
+    assert_eq!(counts, [4, 3]);
+
+    for (part, (pixel, value)) in parts.iter().zip([(0, 255), (15, 128)]) {
+        assert_eq!(part.header.layer_count as usize, part.layers.len());
+        assert_eq!(part.header.bottom_layers, 2);
+        for (layer, original) in part.layers.iter().zip(&file.layers) {
+            let mut expected = [0; 16];
+            expected[pixel] = value;
+            assert_eq!(layer.decode_pixels(4, 4), expected);
+            assert_eq!(layer.layer_position_z, original.layer_position_z);
+            assert_eq!(layer.layer_exposure_time, original.layer_exposure_time);
+        }
+    }
+
+    let mut empty = example_file(2, 1);
+    empty.header.x_resolution = 4;
+    empty.header.y_resolution = 4;
+    assert!(empty.split_objects().is_err());
+}
+
+#[test]
+fn adaptive_motion() {
+    use goo::motion::{LiftSettings, MotionProfile};
+
+    let mut file = example_file(3, 1);
+    file.header.x_resolution = 4;
+    file.header.y_resolution = 4;
+    file.header.x_size = 4.0;
+    file.header.y_size = 4.0;
+    for (i, layer) in file.layers.iter_mut().enumerate() {
+        let mut pixels = [0; 16];
+        pixels[..i * 4].fill(255);
+        layer.set_pixels(4, 4, &pixels);
+    }
+
+    file.layers[2].second_retract_distance = 1.0;
+
+    let lift = |distance, speed| LiftSettings {
+        lift_distance: distance,
+        lift_speed: speed,
+        second_lift_distance: 2.0,
+        second_lift_speed: 200.0,
+    };
+    let profile = MotionProfile {
+        small_area: 0.0,
+        large_area: 8.0,
+        small: lift(2.0, 200.0),
+        large: lift(6.0, 40.0),
+    };
+    file.apply_adaptive_motion(&profile);
+
+    let speeds = file.layers.iter().map(|x| x.lift_speed).collect::<Vec<_>>();
+    assert_eq!(speeds, [LayerContent::default().lift_speed, 120.0, 40.0]);
+    for layer in &file.layers {
+        assert_eq!(
+            layer.lift_distance + layer.second_lift_distance,
+            layer.retract_distance + layer.second_retract_distance
+        );
+    }
+    assert_eq!(file.layers[2].retract_distance, 7.0);
+    assert_eq!(file.layers[2].second_retract_distance, 1.0);
+    assert!(file.header.advance_mode);
+    assert_eq!(file.header.printing_time, file.print_time().round() as u32);
+}
@@ -0,0 +1,36 @@
+use goo::{GooFile, HeaderInfo, LayerContent, Pause};
+
+fn example_file() -> GooFile {
+    let layers = (0..10)
+        .map(|i| LayerContent {
+            layer_position_z: 0.05 * (i + 1) as f32,
+            layer_exposure_time: 2.0,
+            lift_distance: 5.0,
+            lift_speed: 60.0,
+            retract_distance: 5.0,
+            retract_speed: 150.0,
+            ..Default::default()
+        })
+        .collect();
+
+    GooFile::new(HeaderInfo::default(), layers)
+}
+
+#[test]
+fn add_and_clear_pauses() {
+    let mut file = example_file();
+    let base_time = file.print_time();
+
+    file.add_pause(4, 100.0);
+    assert_eq!(file.layers[4].pause(), Pause::Park { position_z: 100.0 });
+    assert_eq!(file.pauses().collect::<Vec<_>>(), [(4, 100.0)]);
+
+    // Parking 99.75mm up at 60mm/min and back down at 150mm/min
+    let park_time = 99.75 + 99.75 * 60.0 / 150.0;
+    assert!((file.print_time() - base_time - park_time).abs() < 1e-2);
+    assert_eq!(file.header.printing_time, file.print_time().round() as u32);
+
+    file.clear_pauses();
+    assert_eq!(file.pauses().count(), 0);
+    assert!((file.print_time() - base_time).abs() < 1e-2);
+}
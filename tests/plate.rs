@@ -0,0 +1,163 @@
+use goo::{plate::MergePolicy, GooFile, HeaderInfo, LayerContent};
+
+mod common;
+
+const WIDTH: u32 = 8;
+const HEIGHT: u32 = 6;
+
+/// A job with a single exposed pixel at `(x, y)` on every layer.
+fn job(layers: u32, exposure_time: f32, (x, y): (u32, u32)) -> GooFile {
+    let header = HeaderInfo {
+        x_resolution: WIDTH as u16,
+        y_resolution: HEIGHT as u16,
+        layer_count: layers,
+        bottom_layers: 1,
+        ..Default::default()
+    };
+
+    let mut pixels = vec![0; (WIDTH * HEIGHT) as usize];
+    pixels[(y * WIDTH + x) as usize] = 255;
+
+    let mut file = common::file(header, &vec![pixels; layers as usize]);
+    for layer in &mut file.layers {
+        layer.layer_exposure_time = exposure_time;
+    }
+    file
+}
+
+fn exposed(layer: &LayerContent) -> Vec<(u32, u32)> {
+    let pixels = layer.decode_pixels(WIDTH, HEIGHT);
+    (0..WIDTH * HEIGHT)
+        .filter(|&i| pixels[i as usize] > 0)
+        .map(|i| (i % WIDTH, i / WIDTH))
+        .collect()
+}
+
+#[test]
+fn merge() {
+    let a = job(3, 2.0, (0, 0));
+    let b = job(5, 3.0, (0, 0));
+
+    let merged =
+        GooFile::merge(&[(&a, (0, 0)), (&b, (4, 2))], MergePolicy::LongestExposure).unwrap();
+    assert_eq!(merged.layers.len(), 5);
+    assert_eq!(merged.header.layer_count, 5);
+    assert_eq!(exposed(&merged.layers[0]), [(0, 0), (4, 2)]);
+    assert_eq!(exposed(&merged.layers[4]), [(4, 2)]);
+    assert_eq!(merged.layers[0].layer_exposure_time, 3.0);
+
+    let merged = GooFile::merge(&[(&a, (0, 0)), (&b, (4, 2))], MergePolicy::First).unwrap();
+    assert_eq!(merged.layers[0].layer_exposure_time, 2.0);
+    assert_eq!(merged.layers[4].layer_exposure_time, 3.0);
+
+    assert!(GooFile::merge(&[(&a, (0, 0)), (&b, (-1, 0))], MergePolicy::First).is_err());
+}
+
+#[test]
+fn merge_bottom_layers_follow_policy() {
+    let a = job(3, 2.0, (0, 0));
+    let mut b = job(5, 3.0, (0, 0));
+    b.header.bottom_layers = 3;
+
+    let jobs = [(&a, (0, 0)), (&b, (4, 2))];
+    let bottom_layers = |policy| GooFile::merge(&jobs, policy).unwrap().header.bottom_layers;
+    assert_eq!(bottom_layers(MergePolicy::LongestExposure), 3);
+    assert_eq!(bottom_layers(MergePolicy::ShortestExposure), 1);
+    assert_eq!(bottom_layers(MergePolicy::First), 1);
+}
+
+#[test]
+fn bounds() {
+    let file = job(2, 2.0, (3, 1));
+    let bounds = file.bounds().unwrap();
+    assert_eq!((bounds.min, bounds.max), ((3, 1), (4, 2)));
+
+    let mut layer = LayerContent::default();
+    let mut pixels = vec![0; (WIDTH * HEIGHT) as usize];
+    pixels[6..11].fill(255);
+    layer.set_pixels(WIDTH, HEIGHT, &pixels);
+    let bounds = layer.bounds(WIDTH, HEIGHT).unwrap();
+    assert_eq!((bounds.min, bounds.max), ((0, 0), (WIDTH, 2)));
+}
+
+#[test]
+fn array() {
+    let mut file = job(2, 2.0, (0, 0));
+    file.header.x_size = WIDTH as f32;
+    file.header.y_size = HEIGHT as f32;
+
+    let arrayed = file.array(3, 2, 1.0).unwrap();
+    assert_eq!(
+        exposed(&arrayed.layers[1]),
+        [(1, 1), (3, 1), (5, 1), (1, 3), (3, 3), (5, 3)]
+    );
+
+    assert!(file.array(5, 1, 1.0).is_err());
+}
+
+#[test]
+fn center() {
+    let file = job(2, 2.0, (0, 0));
+    let centered = file.center().unwrap();
+    assert_eq!(exposed(&centered.layers[0]), [(3, 2)]);
+}
+
+#[test]
+fn center_keeps_parameters() {
+    let mut file = job(3, 2.0, (0, 0));
+    file.header.bottom_layers = 2;
+    file.header.printing_time = 1234;
+    for (i, layer) in file.layers.iter_mut().enumerate() {
+        // Z positions of a part that continues a split print
+        layer.layer_position_z = 1.0 + 0.05 * i as f32;
+        layer.layer_exposure_time = 10.0 - i as f32;
+        layer.lift_distance = 3.0 + i as f32;
+    }
+
+    let centered = file.center().unwrap();
+    assert_eq!(centered.header.bottom_layers, 2);
+    assert_eq!(centered.header.printing_time, 1234);
+    for (layer, original) in centered.layers.iter().zip(&file.layers) {
+        assert_eq!(exposed(layer), [(3, 2)]);
+        assert_eq!(layer.layer_position_z, original.layer_position_z);
+        assert_eq!(layer.layer_exposure_time, original.layer_exposure_time);
+        assert_eq!(layer.lift_distance, original.lift_distance);
+    }
+
+    let mut expected = centered.clone();
+    expected.render_previews();
+    assert_eq!(
+        centered.header.big_preview.inner_data(),
+        expected.header.big_preview.inner_data()
+    );
+    assert_ne!(
+        centered.header.big_preview.inner_data(),
+        file.header.big_preview.inner_data()
+    );
+
+    assert!(file.translate((-1, 0)).is_err());
+}
+
+#[test]
+fn arrange() {
+    let mut file = job(2, 2.0, (0, 0));
+    file.header.x_size = WIDTH as f32;
+    file.header.y_size = HEIGHT as f32;
+
+    // Add a second, 2x2 object in the opposite corner
+    for layer in &mut file.layers {
+        let mut pixels = layer.decode_pixels(WIDTH, HEIGHT);
+        for (x, y) in [(6, 4), (7, 4), (6, 5), (7, 5)] {
+            pixels[(y * WIDTH + x) as usize] = 255;
+        }
+        layer.set_pixels(WIDTH, HEIGHT, &pixels);
+    }
+
+    let arranged = file.arrange(1.0).unwrap();
+    assert_eq!(
+        exposed(&arranged.layers[0]),
+        [(2, 2), (3, 2), (5, 2), (2, 3), (3, 3)]
+    );
+
+    assert!(file.arrange(6.0).is_err());
+}
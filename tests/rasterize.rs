@@ -0,0 +1,71 @@
+use goo::{
+    contour::Polygon,
+    rasterize::{FillRule, Rasterizer},
+    LayerContent, LayerEncoder,
+};
+
+fn rasterize(rasterizer: &Rasterizer, polygons: &[Polygon]) -> Vec<u8> {
+    let mut encoder = LayerEncoder::new();
+    rasterizer.rasterize(polygons, &mut encoder);
+
+    let (data, checksum) = encoder.finish();
+    let layer = LayerContent {
+        data,
+        checksum,
+        ..Default::default()
+    };
+    let (width, height) = rasterizer.resolution;
+    layer.decode_pixels(width, height)
+}
+
+fn square(min: f32, max: f32) -> Polygon {
+    Polygon {
+        points: vec![(min, min), (max, min), (max, max), (min, max)],
+    }
+}
+
+#[test]
+fn fill_rules() {
+    let polygons = [square(1.0, 5.0), square(2.0, 4.0)];
+    let mut rasterizer = Rasterizer::new(6, 6);
+
+    let pixels = rasterize(&rasterizer, &polygons);
+    assert_eq!(pixels.len(), 36);
+    assert_eq!(pixels[6 + 1], 255);
+    assert_eq!(pixels[2 * 6 + 2], 0);
+    assert_eq!(pixels[5 * 6 + 5], 0);
+    assert_eq!(pixels.iter().filter(|&&x| x > 0).count(), 12);
+
+    rasterizer.fill_rule = FillRule::NonZero;
+    let pixels = rasterize(&rasterizer, &polygons);
+    assert_eq!(pixels.iter().filter(|&&x| x > 0).count(), 16);
+}
+
+#[test]
+fn anti_aliasing() {
+    let rasterizer = Rasterizer {
+        pixel_size: (0.5, 0.5),
+        samples: 4,
+        ..Rasterizer::new(4, 1)
+    };
+
+    // Covers pixel 1 fully and half of pixel 2, in mm
+    let polygon = Polygon {
+        points: vec![(0.5, 0.0), (1.25, 0.0), (1.25, 0.5), (0.5, 0.5)],
+    };
+    assert_eq!(rasterize(&rasterizer, &[polygon]), [0, 255, 127, 0]);
+}
+
+#[test]
+fn contour_roundtrip() {
+    let mut pixels = vec![0; 64];
+    for i in [9, 10, 11, 17, 19, 25, 26, 27, 36, 45, 54] {
+        pixels[i] = 255;
+    }
+
+    let mut layer = LayerContent::default();
+    layer.set_pixels(8, 8, &pixels);
+    let contours = layer.contours(8, 8, (1.0, 1.0));
+
+    assert_eq!(rasterize(&Rasterizer::new(8, 8), &contours), pixels);
+}
@@ -0,0 +1,255 @@
+use goo::{
+    slice_config::{ExposureConfig, SliceConfig},
+    slicer::{self, Mesh},
+    GooFile, LayerEncoder,
+};
+
+/// A box between `min` and `max`, with the triangles facing outwards, or inwards if `inverted`.
+fn cuboid(min: [f32; 3], max: [f32; 3], inverted: bool) -> Vec<[[f32; 3]; 3]> {
+    let corner = |i: usize| {
+        [0, 1, 2].map(|axis| match i >> axis & 1 {
+            0 => min[axis],
+            _ => max[axis],
+        })
+    };
+    let center = [0, 1, 2].map(|axis| (min[axis] + max[axis]) / 2.0);
+
+    let faces = [
+        [0, 1, 3, 2],
+        [4, 5, 7, 6],
+        [0, 1, 5, 4],
+        [2, 3, 7, 6],
+        [0, 2, 6, 4],
+        [1, 3, 7, 5],
+    ];
+
+    let mut out = Vec::new();
+    for face in faces {
+        for triangle in [[face[0], face[1], face[2]], [face[0], face[2], face[3]]] {
+            let [a, b, c] = triangle.map(corner);
+            let (u, v) = (
+                [0, 1, 2].map(|i| b[i] - a[i]),
+                [0, 1, 2].map(|i| c[i] - a[i]),
+            );
+            let normal = [
+                u[1] * v[2] - u[2] * v[1],
+                u[2] * v[0] - u[0] * v[2],
+                u[0] * v[1] - u[1] * v[0],
+            ];
+            let outwards = (0..3).map(|i| normal[i] * (a[i] - center[i])).sum::<f32>() > 0.0;
+            out.push(match outwards != inverted {
+                true => [a, b, c],
+                false => [a, c, b],
+            });
+        }
+    }
+    out
+}
+
+fn config() -> SliceConfig {
+    SliceConfig {
+        platform_resolution: [10, 10],
+        platform_size: [10.0, 10.0, 10.0],
+        slice_height: 0.5,
+        exposure_config: ExposureConfig::default(),
+        first_exposure_config: ExposureConfig::default(),
+        first_layers: 1,
+    }
+}
+
+fn exposed(goo: &GooFile, layer: usize) -> Vec<(usize, usize)> {
+    let pixels = goo.layers[layer].decode_pixels(10, 10);
+    (0..100)
+        .filter(|&i| pixels[i] > 0)
+        .map(|i| (i % 10, i / 10))
+        .collect()
+}
+
+#[test]
+fn slice_box() {
+    let config = config();
+    let mut mesh = Mesh {
+        triangles: cuboid([-3.0, 5.0, 2.0], [1.0, 7.0, 3.0], false),
+    };
+    mesh.place_on_plate(&config);
+    assert_eq!(mesh.bounds(), ([3.0, 4.0, 0.0], [7.0, 6.0, 1.0]));
+
+    let goo = GooFile::from_slice_result(slicer::slice::<LayerEncoder>(&mesh, &config));
+    assert_eq!(goo.layers.len(), 2);
+    assert_eq!(goo.header.layer_count, 2);
+
+    let expected = [4, 5].iter().flat_map(|&y| (3..7).map(move |x| (x, y)));
+    assert_eq!(exposed(&goo, 0), expected.collect::<Vec<_>>());
+    assert_eq!(exposed(&goo, 0), exposed(&goo, 1));
+}
+
+#[test]
+fn slice_tube() {
+    let config = config();
+    let mut triangles = cuboid([2.0, 2.0, 0.0], [8.0, 8.0, 1.0], false);
+    triangles.extend(cuboid([4.0, 4.0, 0.0], [6.0, 6.0, 1.0], true));
+
+    let mesh = Mesh { triangles };
+    let goo = GooFile::from_slice_result(slicer::slice::<LayerEncoder>(&mesh, &config));
+
+    let pixels = exposed(&goo, 0);
+    assert_eq!(pixels.len(), 36 - 4);
+    assert!(!pixels.contains(&(4, 4)));
+    assert!(pixels.contains(&(3, 4)));
+}
+
+#[test]
+fn load_stl() {
+    let triangles = cuboid([0.0, 0.0, 0.0], [1.0, 2.0, 3.0], false);
+
+    let mut binary = vec![0; 80];
+    binary.extend_from_slice(&(triangles.len() as u32).to_le_bytes());
+    let mut ascii = String::from("solid cube\n");
+    for triangle in &triangles {
+        binary.extend_from_slice(&[0; 12]);
+        ascii.push_str("facet normal 0 0 0\nouter loop\n");
+        for vertex in triangle {
+            for axis in vertex {
+                binary.extend_from_slice(&axis.to_le_bytes());
+            }
+            ascii.push_str(&format!(
+                "vertex {} {} {}\n",
+                vertex[0], vertex[1], vertex[2]
+            ));
+        }
+        binary.extend_from_slice(&[0; 2]);
+        ascii.push_str("endloop\nendfacet\n");
+    }
+    ascii.push_str("endsolid cube\n");
+
+    assert_eq!(Mesh::load_stl(&binary).unwrap().triangles, triangles);
+    assert_eq!(
+        Mesh::load_stl(ascii.as_bytes()).unwrap().triangles,
+        triangles
+    );
+    assert!(Mesh::load_stl(&binary[..100]).is_err());
+}
+
+#[test]
+fn load_obj() {
+    let obj = "\
+# square split into two triangles, and a quad
+v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+vt 0 0
+f 1/1 2/1 3/1
+f -4//1 -2//1 -1//1
+f 1 2 3 4
+";
+
+    let mesh = Mesh::load_obj(obj).unwrap();
+    let [a, b, c, d] = [
+        [0.0, 0.0, 0.0],
+        [1.0, 0.0, 0.0],
+        [1.0, 1.0, 0.0],
+        [0.0, 1.0, 0.0],
+    ];
+    assert_eq!(mesh.triangles, [[a, b, c], [a, c, d], [a, b, c], [a, c, d]]);
+
+    assert!(Mesh::load_obj("v 0 0 0\nf 1 2 3\n").is_err());
+    assert!(Mesh::load_obj("v 0 0 0\nf 0 1 1\n").is_err());
+}
+
+#[test]
+fn load_3mf() {
+    use std::io::{Cursor, Write};
+    use zip::{write::SimpleFileOptions, ZipWriter};
+
+    let model = r#"<?xml version="1.0" encoding="UTF-8"?>
+<model unit="centimeter" xmlns="http://schemas.microsoft.com/3dmanufacturing/core/2015/02">
+  <resources>
+    <object id="1" type="model">
+      <mesh>
+        <vertices>
+          <vertex x="0" y="0" z="0" />
+          <vertex x="1" y="0" z="0" />
+          <vertex x="0" y="1" z="1" />
+        </vertices>
+        <triangles>
+          <triangle v1="0" v2="1" v3="2" />
+        </triangles>
+      </mesh>
+    </object>
+    <object id="2" type="model">
+      <components>
+        <component objectid="1" transform="1 0 0 0 1 0 0 0 1 0 0 2" />
+      </components>
+    </object>
+  </resources>
+  <build>
+    <item objectid="1" />
+    <item objectid="2" transform="0 1 0 -1 0 0 0 0 1 5 0 0" />
+  </build>
+</model>"#;
+
+    let archive = |model: &str| {
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        zip.start_file("3D/3dmodel.model", SimpleFileOptions::default())
+            .unwrap();
+        zip.write_all(model.as_bytes()).unwrap();
+        zip.finish().unwrap().into_inner()
+    };
+
+    let meshes = Mesh::load_3mf(&archive(model)).unwrap();
+    assert_eq!(meshes.len(), 2);
+    assert_eq!(
+        meshes[0].triangles,
+        [[[0.0, 0.0, 0.0], [10.0, 0.0, 0.0], [0.0, 10.0, 10.0]]]
+    );
+
+    // Lifted 2 cm by the component, then rotated a quarter turn and moved 5 cm along X by the build item
+    assert_eq!(
+        meshes[1].triangles,
+        [[[50.0, 0.0, 20.0], [50.0, 10.0, 20.0], [40.0, 0.0, 30.0]]]
+    );
+
+    let combined = Mesh::combine(meshes);
+    assert_eq!(combined.triangles.len(), 2);
+
+    for broken in [r#"x="0" y="1""#, r#"x="0" y="1" z="one""#] {
+        let model = model.replace(r#"x="0" y="1" z="1""#, broken);
+        let error = Mesh::load_3mf(&archive(&model)).unwrap_err();
+        assert_eq!(error.to_string(), "Vertex 2: invalid `z` attribute");
+    }
+}
+
+#[test]
+fn reconstruct_mesh() {
+    let config = config();
+    let mut triangles = cuboid([2.0, 2.0, 0.0], [8.0, 8.0, 1.0], false);
+    triangles.extend(cuboid([4.0, 4.0, 0.0], [6.0, 6.0, 1.0], true));
+    triangles.extend(cuboid([3.0, 3.0, 1.0], [5.0, 4.0, 2.0], false));
+
+    let goo =
+        GooFile::from_slice_result(slicer::slice::<LayerEncoder>(&Mesh { triangles }, &config));
+    let mesh = goo.to_mesh();
+
+    // The enclosed volume is one box per exposed pixel
+    let volume = mesh
+        .triangles
+        .iter()
+        .map(|[a, b, c]| {
+            (a[0] * (b[1] * c[2] - b[2] * c[1]) - a[1] * (b[0] * c[2] - b[2] * c[0])
+                + a[2] * (b[0] * c[1] - b[1] * c[0]))
+                / 6.0
+        })
+        .sum::<f32>();
+    assert!((volume - (2.0 * (36.0 - 4.0) + 2.0 * 2.0) * 0.5).abs() < 1e-3);
+    assert_eq!(mesh.bounds(), ([2.0, 2.0, 0.0], [8.0, 8.0, 2.0]));
+
+    let stl = Mesh::load_stl(&mesh.to_stl()).unwrap();
+    assert_eq!(stl.triangles, mesh.triangles);
+
+    let sliced = GooFile::from_slice_result(slicer::slice::<LayerEncoder>(&stl, &config));
+    assert_eq!(sliced.layers.len(), goo.layers.len());
+    for layer in 0..goo.layers.len() {
+        assert_eq!(exposed(&sliced, layer), exposed(&goo, layer));
+    }
+}
@@ -0,0 +1,34 @@
+use goo::{GooFile, HeaderInfo, LayerContent};
+
+#[test]
+fn layer_stats() {
+    let header = HeaderInfo {
+        x_resolution: 4,
+        y_resolution: 4,
+        x_size: 2.0,
+        y_size: 2.0,
+        ..Default::default()
+    };
+
+    let mut pixels = [0; 16];
+    pixels[5..7].fill(255);
+    pixels[9] = 51;
+    let mut layer = LayerContent::default();
+    layer.set_pixels(4, 4, &pixels);
+
+    let file = GooFile::new(header, vec![layer]);
+    let stats = &file.stats()[0];
+
+    assert_eq!(stats.exposed_pixels, 3);
+    assert_eq!(stats.histogram[0], 13);
+    assert_eq!(stats.histogram[255], 2);
+    assert_eq!(stats.histogram[51], 1);
+    assert!((stats.area - 2.2 * 0.25).abs() < 1e-5);
+
+    let bounds = stats.bounds.unwrap();
+    assert_eq!((bounds.min, bounds.max), ((1, 1), (3, 3)));
+    assert_eq!(
+        stats.compression_ratio,
+        file.layers[0].data.len() as f32 / 16.0
+    );
+}
@@ -0,0 +1,54 @@
+#![cfg(feature = "image")]
+
+use std::{env, fs};
+
+use goo::{
+    serde::{DynamicSerializer, SizedString},
+    GooFile, HeaderInfo, LayerContent,
+};
+
+fn serialize(goo: &GooFile) -> Vec<u8> {
+    let mut ser = DynamicSerializer::new();
+    goo.serialize(&mut ser);
+    ser.into_inner()
+}
+
+#[test]
+fn unpack_then_pack() -> anyhow::Result<()> {
+    let (width, height) = (4, 3);
+    let mut header = HeaderInfo {
+        x_resolution: width as u16,
+        y_resolution: height as u16,
+        layer_count: 2,
+        ..Default::default()
+    };
+    header.small_preview.set_pixel(1, 2, (1.0, 0.5, 0.25));
+    header.big_preview.set_pixel(10, 20, (0.0, 1.0, 0.0));
+    header.set_field("printer_name", "test printer")?;
+    // Strings with padding after the terminator, invalid UTF-8 and whitespace at the ends are kept byte for byte
+    header.profile_name = SizedString::new(b" \\weird\xff\0junk ");
+
+    let layers = (0..2)
+        .map(|i| {
+            let mut layer = LayerContent {
+                layer_position_z: 0.05 * (i + 1) as f32,
+                layer_exposure_time: 2.5 + i as f32,
+                ..Default::default()
+            };
+            let pixels = (0..width * height)
+                .map(|x| (x * 20 + i) as u8)
+                .collect::<Vec<_>>();
+            layer.set_pixels(width, height, &pixels);
+            layer
+        })
+        .collect();
+    let goo = GooFile::new(header, layers);
+
+    let dir = env::temp_dir().join(format!("goo_unpack_{}", std::process::id()));
+    goo.unpack(&dir)?;
+    let packed = GooFile::pack(&dir)?;
+    fs::remove_dir_all(&dir)?;
+
+    assert_eq!(serialize(&goo), serialize(&packed));
+    Ok(())
+}